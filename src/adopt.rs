@@ -0,0 +1,61 @@
+//! Adoption of torrents added before the earliest import date.
+
+use crate::{
+    config::AdoptConfig,
+    seedbox::SeedboxSet,
+    storage::StorageManager,
+    task::{AdoptOutcome, TaskSummary, TorrentStatus},
+};
+
+/// Import selected torrents added before the earliest import date as ready.
+///
+/// Torrents are otherwise ignored by the pull job, so only completed ones are
+/// adopted; torrents still downloading are reported as incomplete and can be
+/// adopted again once they finish. Torrents already known to the storage are
+/// left untouched.
+///
+/// # Errors
+///
+/// Returns an error if querying a seedbox or the storage fails.
+pub async fn adopt_torrents(
+    store: &StorageManager,
+    seedboxes: &SeedboxSet,
+    selection: &AdoptConfig,
+) -> anyhow::Result<AdoptOutcome> {
+    let earliest_import_date = store.earliest_import_date().await?;
+    let mut outcome = AdoptOutcome::default();
+
+    for (name, seedbox) in seedboxes.iter() {
+        let mut adopted = Vec::new();
+        for (mut torrent, added) in seedbox.tracked_torrents().await? {
+            if added >= earliest_import_date || !selection.is_match(&torrent.hash, added) {
+                continue;
+            }
+            if store.task_status_by_hash(&torrent.hash).await?.is_some() {
+                tracing::trace!(torrent = %torrent.name, hash = %torrent.hash, "Skipping adoption of known torrent");
+                continue;
+            }
+
+            let summary = TaskSummary {
+                hash: torrent.hash.clone(),
+                name: torrent.name.clone(),
+                tag: torrent.tag.clone(),
+                seedbox: name.to_string(),
+            };
+            if torrent.status != TorrentStatus::Seeding {
+                tracing::info!(torrent = %torrent.name, hash = %torrent.hash, "Skipping adoption of incomplete torrent");
+                outcome.incomplete.push(summary);
+                continue;
+            }
+
+            tracing::info!(torrent = %torrent.name, hash = %torrent.hash, seedbox = %name, "Adopting torrent");
+            torrent.seedbox = name.to_string();
+            adopted.push(torrent);
+            outcome.adopted.push(summary);
+        }
+
+        store.update_torrent_info(&adopted).await?;
+    }
+
+    Ok(outcome)
+}
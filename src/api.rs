@@ -3,22 +3,31 @@ use std::sync::Arc;
 use axum::response::IntoResponse;
 use axum::{
     Json, Router,
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{Query, Request, State},
+    http::{StatusCode, header::CONTENT_TYPE},
     response::Response,
     routing::{get, post},
 };
+use qbit_rs::model::{TorrentFile, TorrentSource};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tower_http::trace::TraceLayer;
 use tracing::instrument;
 
-use crate::task::ArtifactInfo;
+use crate::task::{AdoptOutcome, ArtifactInfo, OngoingTransfer, TorrentTaskInfo};
 use crate::{
-    config::DaemonConfig,
+    adopt,
+    config::{AdoptConfig, DaemonConfig},
+    rsync::{RsyncTransmitter, RsyncTransmitterError},
+    seedbox::SeedboxSet,
     storage::{FinalizeArtifactError, StorageManager},
+    upload::{self, UploadError},
 };
 
+/// Upper bound for `.torrent` files and JSON bodies accepted by the upload endpoint.
+const MAX_UPLOAD_SIZE: usize = 16 * 1024 * 1024;
+const TORRENT_FILE_MIME: &str = "application/x-bittorrent";
+
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error("Unauthorized")]
@@ -27,6 +36,10 @@ pub enum ApiError {
     NotFound,
     #[error("Artifact already archived")]
     AlreadyArchived,
+    #[error("No ongoing transfer")]
+    NoOngoingTransfer,
+    #[error("Bad request: {0}")]
+    BadRequest(String),
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -35,8 +48,9 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let status = match self {
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
-            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::NotFound | Self::NoOngoingTransfer => StatusCode::NOT_FOUND,
             Self::AlreadyArchived => StatusCode::CONFLICT,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status, self.to_string()).into_response()
@@ -46,7 +60,26 @@ impl IntoResponse for ApiError {
 #[derive(Clone)]
 pub struct ApiState {
     pub store: Arc<StorageManager>,
+    pub seedboxes: SeedboxSet,
+    pub transmitter: Arc<RsyncTransmitter>,
     pub api_key: String,
+    /// Tracked tags, the first one is applied to uploads without a tag.
+    pub tags: Arc<[String]>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct UploadTorrentRequest {
+    /// Magnet link or URL of a `.torrent` file. A single torrent is accepted
+    /// per request since it is reported back.
+    url: url::Url,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct UploadTorrentParams {
+    /// Tracked tag to apply, defaults to the first configured tag.
+    tag: Option<String>,
+    /// Seedbox to upload to, defaults to the first configured seedbox.
+    seedbox: Option<String>,
 }
 
 fn auth(state: &ApiState, request: &Request) -> Result<(), ApiError> {
@@ -111,6 +144,159 @@ async fn confirm_artifact(
     }
 }
 
+/// List tracked and ready torrents along with their download statistics.
+#[instrument(skip(state, request))]
+async fn list_torrents(
+    State(state): State<ApiState>,
+    request: Request,
+) -> Result<Json<Vec<TorrentTaskInfo>>, ApiError> {
+    auth(&state, &request)?;
+
+    tracing::info!("Requested list of torrents");
+
+    let mut torrents = state.store.list_tracked_torrents().await.map_err(|e| {
+        tracing::error!(error = %e, "Failed to list tracked torrents");
+        ApiError::Internal(e.to_string())
+    })?;
+    let ready = state.store.list_ready_torrents().await.map_err(|e| {
+        tracing::error!(error = %e, "Failed to list ready torrents");
+        ApiError::Internal(e.to_string())
+    })?;
+    torrents.extend(ready);
+
+    Ok(Json(torrents))
+}
+
+/// List ongoing transfers along with their progress.
+#[instrument(skip(state, request))]
+async fn list_transfers(
+    State(state): State<ApiState>,
+    request: Request,
+) -> Result<Json<Vec<OngoingTransfer>>, ApiError> {
+    auth(&state, &request)?;
+
+    tracing::info!("Requested list of ongoing transfers");
+
+    let transfers = state
+        .transmitter
+        .ongoing_transfers()
+        .await
+        .into_iter()
+        .map(|(hash, progress)| OngoingTransfer { hash, progress })
+        .collect();
+
+    Ok(Json(transfers))
+}
+
+/// Cancel the ongoing transfer of a task. The task stays ready for transfer.
+#[instrument(skip(state, request))]
+async fn cancel_transfer(
+    State(state): State<ApiState>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+    request: Request,
+) -> Result<StatusCode, ApiError> {
+    auth(&state, &request)?;
+
+    tracing::info!("Cancelling transfer");
+
+    match state.transmitter.cancel(&hash).await {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(RsyncTransmitterError::NoOngoingTransfer { .. }) => {
+            tracing::warn!(hash = %hash, "No ongoing transfer to cancel");
+            Err(ApiError::NoOngoingTransfer)
+        }
+        Err(e) => Err(ApiError::BadRequest(e.to_string())),
+    }
+}
+
+/// Adopt torrents added before the earliest import date, selected by the
+/// JSON body with `hashes`, `added_after` and/or `added_before`.
+#[instrument(skip(state, request))]
+async fn adopt_torrents(
+    State(state): State<ApiState>,
+    request: Request,
+) -> Result<Json<AdoptOutcome>, ApiError> {
+    auth(&state, &request)?;
+
+    let body = axum::body::to_bytes(request.into_body(), MAX_UPLOAD_SIZE)
+        .await
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let selection: AdoptConfig =
+        serde_json::from_slice(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if selection.is_empty() {
+        return Err(ApiError::BadRequest("No torrent selected".to_string()));
+    }
+
+    tracing::info!("Adopting torrents");
+
+    let outcome = adopt::adopt_torrents(&state.store, &state.seedboxes, &selection)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to adopt torrents");
+            ApiError::Internal(e.to_string())
+        })?;
+
+    Ok(Json(outcome))
+}
+
+/// Add a torrent to the seedbox and start tracking it.
+///
+/// Accepts either a raw `.torrent` file with `Content-Type: application/x-bittorrent`,
+/// or a JSON body whose `url` holds a magnet link or `.torrent` URL. The tag and
+/// the seedbox can be chosen with the `tag` and `seedbox` query parameters.
+#[instrument(skip(state, request))]
+async fn upload_torrent(
+    State(state): State<ApiState>,
+    Query(params): Query<UploadTorrentParams>,
+    request: Request,
+) -> Result<Json<TorrentTaskInfo>, ApiError> {
+    auth(&state, &request)?;
+
+    let is_torrent_file = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes() == TORRENT_FILE_MIME.as_bytes());
+    let body = axum::body::to_bytes(request.into_body(), MAX_UPLOAD_SIZE)
+        .await
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let source = if is_torrent_file {
+        TorrentSource::TorrentFiles {
+            torrents: vec![TorrentFile {
+                filename: "upload.torrent".to_string(),
+                data: body.to_vec(),
+            }],
+        }
+    } else {
+        let payload: UploadTorrentRequest =
+            serde_json::from_slice(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        TorrentSource::Urls {
+            urls: vec![payload.url].into(),
+        }
+    };
+
+    let torrent = upload::upload_torrent(
+        &state.store,
+        &state.seedboxes,
+        &state.tags,
+        &source,
+        params.tag.as_deref(),
+        params.seedbox.as_deref(),
+    )
+    .await
+    .map_err(|e| match e {
+        UploadError::UntrackedTag(_) | UploadError::UnknownSeedbox(_) => {
+            ApiError::BadRequest(e.to_string())
+        }
+        UploadError::Seedbox(_) | UploadError::Storage(_) => {
+            tracing::error!(error = %e, "Failed to upload torrent");
+            ApiError::Internal(e.to_string())
+        }
+    })?;
+
+    Ok(Json(torrent))
+}
+
 struct ApiServerInner {
     addr: std::net::SocketAddr,
 }
@@ -123,10 +309,18 @@ pub struct ApiServer {
 
 impl ApiServer {
     /// Create a new API server from the given configuration.
-    pub fn from_config(config: &DaemonConfig, store: Arc<StorageManager>) -> Self {
+    pub fn from_config(
+        config: &DaemonConfig,
+        store: Arc<StorageManager>,
+        seedboxes: SeedboxSet,
+        transmitter: Arc<RsyncTransmitter>,
+    ) -> Self {
         let api_state = ApiState {
             store,
+            seedboxes,
+            transmitter,
             api_key: config.api.key.clone(),
+            tags: config.tag_names().into(),
         };
 
         let addr = config.api.addr;
@@ -156,6 +350,10 @@ impl ApiServer {
             let router = Router::new()
                 .route("/api/artifacts", get(list_artifacts))
                 .route("/api/artifacts/{hash}/confirm", post(confirm_artifact))
+                .route("/api/torrents", get(list_torrents).post(upload_torrent))
+                .route("/api/torrents/adopt", post(adopt_torrents))
+                .route("/api/transfers", get(list_transfers))
+                .route("/api/tasks/{hash}/cancel", post(cancel_transfer))
                 .with_state(api_state)
                 .layer(TraceLayer::new_for_http());
 
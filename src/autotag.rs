@@ -0,0 +1,175 @@
+//! Rules applying a tracked tag to untagged torrents.
+
+use regex::Regex;
+
+use crate::{config::AutoTagRule, seedbox::UntrackedTorrent};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AutoTagRuleError {
+    #[error("rule #{index} has no condition")]
+    NoCondition { index: usize },
+    #[error("name pattern of rule #{index} is invalid: {source}")]
+    InvalidName {
+        index: usize,
+        #[source]
+        source: regex::Error,
+    },
+}
+
+/// Compiled `[[auto_tag]]` rules, the first matching rule wins.
+pub struct AutoTagger {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    name: Option<Regex>,
+    category: Option<String>,
+    tracker: Option<String>,
+    save_path: Option<String>,
+    tag: String,
+}
+
+impl AutoTagger {
+    /// Compile the rules, applying `default_tag` to rules without a tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rule has no condition or an invalid name pattern.
+    pub fn from_config(rules: &[AutoTagRule], default_tag: &str) -> Result<Self, AutoTagRuleError> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                if rule.name.is_none()
+                    && rule.category.is_none()
+                    && rule.tracker.is_none()
+                    && rule.save_path.is_none()
+                {
+                    return Err(AutoTagRuleError::NoCondition { index });
+                }
+
+                let name = rule
+                    .name
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|source| AutoTagRuleError::InvalidName { index, source })?;
+
+                Ok(Rule {
+                    name,
+                    category: rule.category.clone(),
+                    tracker: rule.tracker.clone(),
+                    save_path: rule.save_path.clone(),
+                    tag: rule.tag.clone().unwrap_or_else(|| default_tag.to_string()),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Whether no rule is configured.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Find the first rule matching a torrent, returning its index and tag.
+    pub fn find(&self, torrent: &UntrackedTorrent) -> Option<(usize, &str)> {
+        self.rules
+            .iter()
+            .position(|rule| rule.is_match(torrent))
+            .map(|index| (index, self.rules[index].tag.as_str()))
+    }
+}
+
+impl Rule {
+    fn is_match(&self, torrent: &UntrackedTorrent) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|name| name.is_match(&torrent.name))
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|category| torrent.category.as_ref() == Some(category))
+            && self.tracker.as_ref().is_none_or(|tracker| {
+                torrent
+                    .tracker
+                    .as_ref()
+                    .is_some_and(|t| t.contains(tracker.as_str()))
+            })
+            && self.save_path.as_ref().is_none_or(|prefix| {
+                torrent
+                    .save_path
+                    .as_ref()
+                    .is_some_and(|path| path.starts_with(prefix.as_str()))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(name: &str, category: Option<&str>, tracker: Option<&str>) -> UntrackedTorrent {
+        UntrackedTorrent {
+            hash: "aaaa".to_string(),
+            name: name.to_string(),
+            category: category.map(ToString::to_string),
+            tracker: tracker.map(ToString::to_string),
+            save_path: Some("/downloads/anime".to_string()),
+        }
+    }
+
+    #[test]
+    fn first_rule_with_all_conditions_matching_wins() {
+        let rules = vec![
+            AutoTagRule {
+                name: Some(r"(?i)\bbatch\b".to_string()),
+                tracker: Some("nyaa.tracker.wf".to_string()),
+                tag: Some("anime-archive".to_string()),
+                ..AutoTagRule::default()
+            },
+            AutoTagRule {
+                category: Some("anime".to_string()),
+                ..AutoTagRule::default()
+            },
+            AutoTagRule {
+                save_path: Some("/downloads/anime".to_string()),
+                tag: Some("anime-misc".to_string()),
+                ..AutoTagRule::default()
+            },
+        ];
+        let tagger = AutoTagger::from_config(&rules, "anipler").unwrap();
+        let tracker = Some("http://nyaa.tracker.wf:7777/announce");
+
+        assert_eq!(
+            tagger.find(&torrent("[Group] Show (Batch)", None, tracker)),
+            Some((0, "anime-archive"))
+        );
+        assert_eq!(
+            tagger.find(&torrent("[Group] Show - 01", Some("anime"), tracker)),
+            Some((1, "anipler"))
+        );
+        assert_eq!(
+            tagger.find(&torrent("[Group] Show - 01", None, None)),
+            Some((2, "anime-misc"))
+        );
+
+        let mut other = torrent("Show - 01", None, None);
+        other.save_path = Some("/downloads/movies".to_string());
+        assert_eq!(tagger.find(&other), None);
+    }
+
+    #[test]
+    fn rules_without_condition_are_rejected() {
+        let rules = vec![AutoTagRule {
+            tag: Some("anipler".to_string()),
+            ..AutoTagRule::default()
+        }];
+
+        assert!(matches!(
+            AutoTagger::from_config(&rules, "anipler"),
+            Err(AutoTagRuleError::NoCondition { index: 0 })
+        ));
+    }
+}
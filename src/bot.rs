@@ -1,5 +1,6 @@
 use std::{cmp::min, fmt::Write, sync::Arc, time::Duration};
 
+use chrono::NaiveDate;
 use frankenstein::{
     AsyncTelegramApi,
     methods::{GetUpdatesParams, SendMessageParams, SetMyCommandsParams},
@@ -12,8 +13,12 @@ use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    config::DaemonConfig,
-    task::{ArtifactInfo, TransferState, TransferTaskInfo},
+    config::{AdoptConfig, DaemonConfig, RssRule},
+    task::{
+        AdoptOutcome, ArtifactInfo, FailedTask, ManagedRssRule, SeedboxSpace, TaskSummary,
+        TorrentTaskInfo, TransferFailure, TransferProgress, TransferState, TransferTaskInfo,
+        UnhealthyTask,
+    },
 };
 
 #[derive(Debug)]
@@ -24,32 +29,117 @@ pub struct ReportTorrentInfo {
     pub name: String,
     /// Current transfer state for report rendering.
     pub transfer_state: TransferState,
+    /// Progress of an ongoing transfer.
+    pub transfer_progress: Option<TransferProgress>,
 }
 
 #[derive(Debug)]
 pub enum BotCommand {
     PullJob,
     TransferJob,
+    /// Transfer the ready torrents matching hash prefixes next.
+    PriorityTransfer(Vec<String>),
+    /// Cancel the ongoing transfer matching a hash prefix.
+    CancelTransfer(String),
+    /// Retry failed transfers matching a hash prefix, or all of them.
+    Retry(Option<String>),
     ReportAvailable,
+    /// Adopt torrents added before the earliest import date.
+    Adopt(AdoptConfig),
+    /// Manage RSS auto-download rules.
+    Rss(RssCommand),
+}
+
+#[derive(Debug)]
+pub enum RssCommand {
+    List,
+    Add(RssRule),
+    Remove(String),
 }
 
 impl BotCommand {
     fn parse(input: &str) -> Option<Self> {
         let body = input.strip_prefix('/')?;
-        let (cmd, _body) = match body.split_once(' ') {
+        let (cmd, body) = match body.split_once(' ') {
             Some((c, b)) => (c, b),
             None => (body, ""),
         };
 
         match cmd {
             "pull" => Some(Self::PullJob),
-            "transfer" => Some(Self::TransferJob),
+            "transfer" => {
+                let prefixes = body
+                    .split_whitespace()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                if prefixes.is_empty() {
+                    Some(Self::TransferJob)
+                } else {
+                    Some(Self::PriorityTransfer(prefixes))
+                }
+            }
+            "cancel" => body
+                .split_whitespace()
+                .next()
+                .map(|prefix| Self::CancelTransfer(prefix.to_string())),
+            "retry" => Some(Self::Retry(
+                body.split_whitespace().next().map(ToString::to_string),
+            )),
             "report" => Some(Self::ReportAvailable),
+            "adopt" => parse_adopt(body).map(Self::Adopt),
+            "rss" => parse_rss(body).map(Self::Rss),
             _ => None,
         }
     }
 }
 
+/// Parse arguments of `/rss`, one of `list`, `add <name> <feed-url> <must-contain>`
+/// or `remove <name>`.
+fn parse_rss(body: &str) -> Option<RssCommand> {
+    let mut args = body.split_whitespace();
+    match args.next() {
+        None | Some("list") => Some(RssCommand::List),
+        Some("add") => {
+            let name = args.next()?.to_string();
+            let feed = args.next()?.to_string();
+            let must_contain = args.collect::<Vec<_>>().join(" ");
+            if must_contain.is_empty() {
+                return None;
+            }
+            Some(RssCommand::Add(RssRule {
+                name,
+                feeds: vec![feed],
+                must_contain,
+                ..RssRule::default()
+            }))
+        }
+        Some("remove") => args.next().map(|name| RssCommand::Remove(name.to_string())),
+        Some(_) => None,
+    }
+}
+
+/// Parse arguments of `/adopt`, a list of hashes and/or one date range such as
+/// `2025-01-01..2025-03-31`, where either end may be omitted.
+fn parse_adopt(body: &str) -> Option<AdoptConfig> {
+    let mut selection = AdoptConfig::default();
+    for arg in body.split_whitespace() {
+        if let Some((after, before)) = arg.split_once("..") {
+            let parse_date = |date: &str| -> Option<Option<NaiveDate>> {
+                if date.is_empty() {
+                    return Some(None);
+                }
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(Some)
+            };
+            selection.added_after = parse_date(after)?;
+            selection.added_before = parse_date(before)?;
+        } else {
+            selection.hashes.push(arg.to_string());
+        }
+    }
+
+    (!selection.is_empty()).then_some(selection)
+}
+
 #[derive(Debug, Error)]
 pub enum TelegramBotError {
     #[error("bot not running")]
@@ -228,7 +318,17 @@ impl TelegramBot {
 
         let transfer_command = frankenstein::types::BotCommand::builder()
             .command("transfer")
-            .description("Transfer torrents from seedbox to relay.")
+            .description("Transfer torrents from seedbox to relay, given hash prefixes first.")
+            .build();
+
+        let cancel_command = frankenstein::types::BotCommand::builder()
+            .command("cancel")
+            .description("Cancel the ongoing transfer of a hash prefix.")
+            .build();
+
+        let retry_command = frankenstein::types::BotCommand::builder()
+            .command("retry")
+            .description("Retry failed transfers, all or of a hash prefix.")
             .build();
 
         let report_command = frankenstein::types::BotCommand::builder()
@@ -236,8 +336,26 @@ impl TelegramBot {
             .description("Report available torrents and artifacts.")
             .build();
 
+        let adopt_command = frankenstein::types::BotCommand::builder()
+            .command("adopt")
+            .description("Adopt old torrents by hash or date range, e.g. 2025-01-01..2025-03-31.")
+            .build();
+
+        let rss_command = frankenstein::types::BotCommand::builder()
+            .command("rss")
+            .description("List, add <name> <feed> <pattern> or remove <name> RSS rules.")
+            .build();
+
         let params = SetMyCommandsParams::builder()
-            .commands(vec![pull_command, transfer_command, report_command])
+            .commands(vec![
+                pull_command,
+                transfer_command,
+                cancel_command,
+                retry_command,
+                report_command,
+                adopt_command,
+                rss_command,
+            ])
             .scope(BotCommandScope::Chat(BotCommandScopeChat {
                 chat_id: self.chat_id.into(),
             }))
@@ -280,26 +398,57 @@ impl TelegramBot {
     /// Returns an error if building or sending the message fails.
     pub async fn report_available(
         &self,
+        tracked: &[TorrentTaskInfo],
         torrents: &[ReportTorrentInfo],
         artifacts: &[ArtifactInfo],
+        missing: &[TaskSummary],
+        failed: &[FailedTask],
+        free_space: &[SeedboxSpace],
     ) -> anyhow::Result<()> {
         tracing::debug!(
+            tracked = tracked.len(),
             torrents = torrents.len(),
             artifacts = artifacts.len(),
+            missing = missing.len(),
             "Generating availability report"
         );
 
         let mut text = String::new();
 
+        if !tracked.is_empty() {
+            writeln!(text, "Tracked Torrents:")?;
+            for torrent in tracked {
+                let progress = torrent.stats.progress * 100.0;
+                write!(text, "\n- {} (", torrent.name)?;
+                if torrent.status.is_unhealthy() {
+                    write!(text, "{}, ", torrent.status)?;
+                }
+                write!(text, "{progress:.0}%")?;
+                match torrent.stats.eta {
+                    Some(eta) if !(0..INFINITE_ETA).contains(&eta) => write!(text, ", ETA ∞")?,
+                    Some(eta) => write!(text, ", ETA {}", format_duration(eta))?,
+                    None => {}
+                }
+                writeln!(text, ")\n  ({})", torrent.hash)?;
+            }
+            writeln!(text)?;
+        }
+
         if torrents.is_empty() {
             writeln!(text, "No torrent available")?;
         } else {
             writeln!(text, "Ready Torrents:")?;
             for torrent in torrents {
-                let transfer_state = match torrent.transfer_state {
-                    TransferState::Ongoing => " (transfer in progress)",
-                    TransferState::Queued => " (queued)",
-                    TransferState::None => "",
+                let transfer_state = match (torrent.transfer_state, torrent.transfer_progress) {
+                    (TransferState::Ongoing, Some(progress)) => format!(
+                        " (transfer {}%, {}/s, ETA {})",
+                        progress.percent,
+                        format_size(progress.bytes_per_sec),
+                        format_duration(progress.eta)
+                    ),
+                    (TransferState::Ongoing, None) => " (transfer in progress)".to_string(),
+                    (TransferState::Queued, _) => " (queued)".to_string(),
+                    (TransferState::None, _) => String::new(),
                 };
                 writeln!(
                     text,
@@ -321,9 +470,232 @@ impl TelegramBot {
             }
         }
 
+        if !missing.is_empty() {
+            writeln!(text, "\nMissing from Seedbox:")?;
+            for task in missing {
+                writeln!(text, "\n- {}\n  ({})", task.name, task.hash)?;
+            }
+        }
+
+        if !failed.is_empty() {
+            writeln!(text, "\nFailed Transfers (use /retry):")?;
+            for task in failed {
+                writeln!(
+                    text,
+                    "\n- {} ({} attempts)\n  ({})",
+                    task.name, task.attempts, task.hash
+                )?;
+                if let Some(error) = &task.last_error {
+                    writeln!(text, "  {error}")?;
+                }
+            }
+        }
+
+        if !free_space.is_empty() {
+            writeln!(text, "\nSeedbox Free Space:")?;
+            for space in free_space {
+                writeln!(
+                    text,
+                    "- {}: {}",
+                    space.seedbox,
+                    format_size(space.free_space)
+                )?;
+            }
+        }
+
         self.send_text(&text).await
     }
 
+    /// Alert user that a tracked torrent has been stalled or errored for a while.
+    pub async fn notify_unhealthy(&self, task: &UnhealthyTask) {
+        let elapsed = (chrono::Utc::now() - task.since).num_seconds();
+        let text = format!(
+            "Torrent {} for {}:\n- {}\n  ({})",
+            task.status,
+            format_duration(elapsed),
+            task.name,
+            task.hash
+        );
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, torrent = %task.name, hash = %task.hash, "Failed to notify unhealthy torrent"));
+    }
+
+    /// Alert user that a seedbox is running out of space.
+    pub async fn notify_low_space(&self, seedbox: &str, free_space: u64, paused: usize) {
+        let mut text = format!(
+            "Seedbox {seedbox} is low on space: {} free",
+            format_size(free_space)
+        );
+        if paused > 0 {
+            let _ = write!(text, "\nPaused {paused} downloads until space is freed up");
+        }
+        let _ = self.send_text(&text).await.inspect_err(
+            |e| tracing::warn!(error = ?e, seedbox = %seedbox, "Failed to notify low space"),
+        );
+    }
+
+    /// Notify user that downloads paused for low space have been resumed.
+    pub async fn notify_downloads_resumed(&self, seedbox: &str, free_space: u64, resumed: usize) {
+        let text = format!(
+            "Resumed {resumed} downloads on seedbox {seedbox}, {} free",
+            format_size(free_space)
+        );
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, seedbox = %seedbox, "Failed to notify resumed downloads"));
+    }
+
+    /// Report the result of adopting old torrents.
+    pub async fn notify_adopted(&self, outcome: &AdoptOutcome) {
+        let mut text = if outcome.adopted.is_empty() {
+            "No torrent adopted".to_string()
+        } else {
+            "Adopted torrents:".to_string()
+        };
+        for task in &outcome.adopted {
+            let _ = write!(text, "\n- {}\n  ({})", task.name, task.hash);
+        }
+        if !outcome.incomplete.is_empty() {
+            text.push_str("\n\nSkipped incomplete torrents:");
+            for task in &outcome.incomplete {
+                let _ = write!(text, "\n- {}\n  ({})", task.name, task.hash);
+            }
+        }
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, count = outcome.adopted.len(), "Failed to notify adopted torrents"));
+    }
+
+    /// List RSS rules managed by anipler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if building or sending the message fails.
+    pub async fn report_rss_rules(&self, rules: &[ManagedRssRule]) -> anyhow::Result<()> {
+        if rules.is_empty() {
+            return self.send_text("No RSS rule managed").await;
+        }
+
+        let mut text = "RSS Rules:".to_string();
+        for managed in rules {
+            let rule = &managed.rule;
+            write!(text, "\n\n- {}", rule.name)?;
+            if managed.from_config {
+                write!(text, " (config)")?;
+            }
+            write!(text, "\n  Must contain: {}", rule.must_contain)?;
+            for feed in &rule.feeds {
+                write!(text, "\n  Feed: {feed}")?;
+            }
+        }
+
+        self.send_text(&text).await
+    }
+
+    /// Notify user about the result of changing an RSS rule.
+    pub async fn notify_rss_rule(&self, name: &str, result: Result<&str, String>) {
+        let text = match result {
+            Ok(change) => format!("RSS rule {name} {change}"),
+            Err(reason) => format!("Failed to update RSS rule {name}: {reason}"),
+        };
+        let _ = self.send_text(&text).await.inspect_err(
+            |e| tracing::warn!(error = ?e, rule = %name, "Failed to notify RSS rule update"),
+        );
+    }
+
+    /// Notify user that torrents changed on the seedbox after their transfer.
+    pub async fn notify_content_changed(&self, tasks: &[TaskSummary], requeued: bool) {
+        let mut text = if requeued {
+            "Torrent content changed, transferring again:".to_string()
+        } else {
+            "Torrent content changed after transfer:".to_string()
+        };
+        for task in tasks {
+            let _ = write!(text, "\n- {}\n  ({})", task.name, task.hash);
+        }
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, count = tasks.len(), "Failed to notify changed torrents"));
+    }
+
+    /// Notify user that torrents vanished from the seedbox.
+    pub async fn notify_missing(&self, tasks: &[TaskSummary]) {
+        let mut text = "Torrents missing from seedbox:".to_string();
+        for task in tasks {
+            let _ = write!(text, "\n- {}\n  ({})", task.name, task.hash);
+        }
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, count = tasks.len(), "Failed to notify missing torrents"));
+    }
+
+    /// Notify user which torrents are transferred next on request.
+    pub async fn notify_priority_transfer(&self, tasks: &[TransferTaskInfo], unmatched: &[String]) {
+        let mut text = String::new();
+        if !tasks.is_empty() {
+            text.push_str("Transferring next:");
+            for task in tasks {
+                let _ = write!(text, "\n- {}\n  ({})", task.name, task.hash);
+            }
+        }
+        if !unmatched.is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            let _ = write!(
+                text,
+                "No single ready torrent matches: {}",
+                unmatched.join(", ")
+            );
+        }
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, count = tasks.len(), "Failed to notify priority transfer"));
+    }
+
+    /// Reply to a transfer cancellation request with the cancelled hash.
+    pub async fn notify_cancel_requested(&self, result: Result<String, String>) {
+        let text = match &result {
+            Ok(hash) => format!("Cancelling transfer of {hash}"),
+            Err(reason) => format!("Failed to cancel transfer: {reason}"),
+        };
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, "Failed to reply to cancel request"));
+    }
+
+    /// Notify user that a transfer was cancelled.
+    pub async fn notify_transfer_cancelled(&self, task: &TransferTaskInfo) {
+        let text = format!(
+            "Transfer cancelled, use /transfer {} to resume:\n{}\n({})",
+            task.hash, task.name, task.hash
+        );
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, torrent = %task.name, hash = %task.hash, "Failed to notify transfer cancellation"));
+    }
+
+    /// Reply to a retry request that matched no failed transfer.
+    pub async fn notify_nothing_to_retry(&self, prefix: Option<&str>) {
+        let text = match prefix {
+            Some(prefix) => format!("No failed transfer matches {prefix}"),
+            None => "No failed transfer to retry".to_string(),
+        };
+        let _ = self
+            .send_text(&text)
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, "Failed to reply to retry request"));
+    }
+
     /// Notify user that a transfer has started.
     pub async fn notify_transfer_start(&self, task: &TransferTaskInfo) {
         let text = format!("Transfer started:\n- {}\n  ({})", task.name, task.hash);
@@ -343,11 +715,42 @@ impl TelegramBot {
     }
 
     /// Notify user that a transfer has failed.
-    pub async fn notify_transfer_failure(&self, task: &TransferTaskInfo, reason: &str) {
-        let text = format!(
+    ///
+    /// With the recorded `failure`, the message says when the transfer is
+    /// retried, or that it needs a manual retry.
+    pub async fn notify_transfer_failure(
+        &self,
+        task: &TransferTaskInfo,
+        reason: &str,
+        failure: Option<&TransferFailure>,
+    ) {
+        let mut text = format!(
             "Transfer failed:\n- {}\n  ({})\nReason: {reason}",
             task.name, task.hash
         );
+        match failure {
+            Some(TransferFailure {
+                attempts,
+                retry_at: Some(retry_at),
+            }) => {
+                let _ = write!(
+                    text,
+                    "\nAttempt {attempts}, retrying after {}",
+                    retry_at.format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+            Some(TransferFailure {
+                attempts,
+                retry_at: None,
+            }) => {
+                let _ = write!(
+                    text,
+                    "\nNot retrying automatically after {attempts} attempt(s), use /retry {} to try again",
+                    task.hash
+                );
+            }
+            None => {}
+        }
         let _ = self
             .send_text(&text)
             .await
@@ -365,3 +768,140 @@ impl TelegramBot {
         Ok(())
     }
 }
+
+/// qBittorrent reports this ETA for torrents which will never complete.
+const INFINITE_ETA: i64 = 8_640_000;
+
+/// Format a duration or ETA in seconds, e.g. `1d 2h`, `3h 4m` or `12m`.
+fn format_duration(secs: i64) -> String {
+    let minutes = secs.max(0) / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) if minutes == 0 => "<1m".to_string(),
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+/// Format a size in bytes with a binary unit, e.g. `512 MiB` or `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Only used for display.
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+
+    if size < 10.0 {
+        format!("{size:.1} {unit}")
+    } else {
+        format!("{size:.0} {unit}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_duration_picks_two_largest_units() {
+        assert_eq!(format_duration(30), "<1m");
+        assert_eq!(format_duration(12 * 60 + 5), "12m");
+        assert_eq!(format_duration(3 * 3600 + 4 * 60), "3h 4m");
+        assert_eq!(format_duration(26 * 3600 + 59 * 60), "1d 2h");
+        // Only qBittorrent ETAs treat this as infinite.
+        assert_eq!(format_duration(INFINITE_ETA), "100d 0h");
+    }
+
+    #[test]
+    fn adopt_command_parses_hashes_and_date_range() {
+        let Some(BotCommand::Adopt(selection)) = BotCommand::parse("/adopt abcd 2025-01-01.. ef01")
+        else {
+            panic!("expected adopt command");
+        };
+        assert_eq!(selection.hashes, vec!["abcd", "ef01"]);
+        assert_eq!(selection.added_after, NaiveDate::from_ymd_opt(2025, 1, 1));
+        assert!(selection.added_before.is_none());
+
+        assert!(BotCommand::parse("/adopt").is_none());
+        assert!(BotCommand::parse("/adopt 2025-13-01..").is_none());
+    }
+
+    #[test]
+    fn transfer_command_parses_hash_prefixes() {
+        assert!(matches!(
+            BotCommand::parse("/transfer"),
+            Some(BotCommand::TransferJob)
+        ));
+        assert!(matches!(
+            BotCommand::parse("/transfer abc123 def456"),
+            Some(BotCommand::PriorityTransfer(prefixes)) if prefixes == ["abc123", "def456"]
+        ));
+    }
+
+    #[test]
+    fn retry_command_takes_optional_prefix() {
+        assert!(matches!(
+            BotCommand::parse("/retry"),
+            Some(BotCommand::Retry(None))
+        ));
+        assert!(matches!(
+            BotCommand::parse("/retry abc123"),
+            Some(BotCommand::Retry(Some(prefix))) if prefix == "abc123"
+        ));
+    }
+
+    #[test]
+    fn cancel_command_requires_hash_prefix() {
+        assert!(matches!(
+            BotCommand::parse("/cancel abc123"),
+            Some(BotCommand::CancelTransfer(prefix)) if prefix == "abc123"
+        ));
+        assert!(BotCommand::parse("/cancel").is_none());
+    }
+
+    #[test]
+    fn rss_command_parses_subcommands() {
+        assert!(matches!(
+            BotCommand::parse("/rss"),
+            Some(BotCommand::Rss(RssCommand::List))
+        ));
+
+        let Some(BotCommand::Rss(RssCommand::Add(rule))) =
+            BotCommand::parse("/rss add show https://feed.example/rss [Group] Show 1080p")
+        else {
+            panic!("expected rss add command");
+        };
+        assert_eq!(rule.name, "show");
+        assert_eq!(rule.feeds, vec!["https://feed.example/rss"]);
+        assert_eq!(rule.must_contain, "[Group] Show 1080p");
+
+        assert!(matches!(
+            BotCommand::parse("/rss remove show"),
+            Some(BotCommand::Rss(RssCommand::Remove(name))) if name == "show"
+        ));
+        assert!(BotCommand::parse("/rss add show https://feed.example/rss").is_none());
+    }
+
+    #[test]
+    fn format_size_uses_binary_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536 * 1024 * 1024), "1.5 GiB");
+        assert_eq!(format_size(300 * 1024 * 1024), "300 MiB");
+        assert_eq!(
+            format_size(5 * 1024 * 1024 * 1024 * 1024 * 1024),
+            "5120 TiB"
+        );
+    }
+}
@@ -1,14 +1,20 @@
 use std::{
+    collections::{HashMap, HashSet},
     env, fs, io,
     net::SocketAddr,
     path::{Path, PathBuf},
 };
 
 use ::config::{Config, ConfigBuilder, Environment, File, FileFormat, builder::DefaultState};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Parser;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::{
+    autotag::AutoTagger, filter::FileFilter, rsync::TransferBackend, seedbox::SeedboxBackend,
+};
+
 const ENV_PREFIX: &str = "ANIPLER";
 const DAEMON_CONFIG_PATH_ENV: &str = "ANIPLER_DAEMON_CONFIG_PATH";
 const PULLER_CONFIG_PATH_ENV: &str = "ANIPLER_PULLER_CONFIG_PATH";
@@ -16,6 +22,16 @@ const PULLER_CONFIG_PATH_ENV: &str = "ANIPLER_PULLER_CONFIG_PATH";
 const DEFAULT_PULL_CRON: &str = "0 0/30 * * * *";
 const DEFAULT_TRANSFER_CRON: &str = "0 0 * * * *";
 const DEFAULT_API_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_TORRENT_TAG: &str = "anipler";
+const DEFAULT_UNHEALTHY_AFTER: u64 = 120;
+const DEFAULT_SEEDBOX_NAME: &str = "default";
+const DEFAULT_MAX_CONCURRENT: usize = 1;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_BACKOFF: u64 = 30;
+/// Upper bound of the delay between transfer attempts in minutes.
+const MAX_RETRY_DELAY: u64 = 24 * 60;
+const DEFAULT_RTORRENT_TAG_FIELD: &str = "custom1";
+const RTORRENT_TAG_FIELDS: &[&str] = &["custom1", "custom2", "custom3", "custom4", "custom5"];
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigLoadError {
@@ -33,6 +49,11 @@ pub enum ConfigLoadError {
     },
     #[error("{field} path {path} must point to an existing file")]
     PathValidation { field: &'static str, path: PathBuf },
+    #[error("[{section}] section is required by the backend of seedbox {seedbox}")]
+    MissingSection {
+        seedbox: String,
+        section: &'static str,
+    },
 }
 
 impl From<::config::ConfigError> for ConfigLoadError {
@@ -56,13 +77,238 @@ pub struct DaemonArgs {
 pub struct DaemonConfig {
     pub pull_cron: String,
     pub transfer_cron: String,
-    pub qbit: QBitConfig,
+    /// Backend section of the single `[seedbox]`, merged into `seedboxes` on load.
+    #[serde(default)]
+    qbit: Option<QBitConfig>,
+    /// Backend section of the single `[seedbox]`, merged into `seedboxes` on load.
+    #[serde(default)]
+    transmission: Option<TransmissionConfig>,
+    /// Backend section of the single `[seedbox]`, merged into `seedboxes` on load.
+    #[serde(default)]
+    deluge: Option<DelugeConfig>,
+    /// Backend section of the single `[seedbox]`, merged into `seedboxes` on load.
+    #[serde(default)]
+    rtorrent: Option<RTorrentConfig>,
     pub storage_path: PathBuf,
     pub stateless: bool,
-    pub seedbox: SeedboxConfig,
+    /// Single seedbox of configurations predating `[[seedboxes]]`, merged into
+    /// `seedboxes` under the name `default` on load.
+    #[serde(default)]
+    seedbox: Option<SeedboxConfig>,
+    /// Seedboxes managed by the daemon, the first one receives uploads by default.
+    #[serde(default)]
+    pub seedboxes: Vec<SeedboxConfig>,
     pub transfer: TransferConfig,
     pub telegram: TelegramConfig,
     pub api: ApiConfig,
+    /// Tracked tags in order of precedence, the first one is used for uploads.
+    #[serde(default = "default_tags")]
+    pub tags: Vec<TagPolicy>,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    /// Rules applying a tracked tag to untagged torrents, in order of precedence.
+    #[serde(default)]
+    pub auto_tag: Vec<AutoTagRule>,
+    /// Torrents added before the earliest import date to adopt on startup.
+    #[serde(default)]
+    pub adopt: AdoptConfig,
+    /// qBittorrent RSS auto-download rules synced on startup.
+    #[serde(default)]
+    pub rss: Vec<RssRule>,
+}
+
+/// qBittorrent RSS auto-download rule managed by anipler.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RssRule {
+    /// Unique rule name in qBittorrent.
+    pub name: String,
+    /// URLs of the feeds the rule applies to.
+    pub feeds: Vec<String>,
+    /// Words, or a regular expression with `use_regex`, article titles must contain.
+    pub must_contain: String,
+    #[serde(default)]
+    pub must_not_contain: String,
+    #[serde(default)]
+    pub use_regex: bool,
+    /// qBittorrent episode filter, e.g. `1x12-;`.
+    #[serde(default)]
+    pub episode_filter: String,
+    /// Tracked tag applied to downloaded torrents, defaults to the first configured tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub save_path: Option<String>,
+    /// Seedbox to sync the rule to, defaults to the first configured seedbox.
+    #[serde(default)]
+    pub seedbox: Option<String>,
+}
+
+/// Rule applying a tracked tag to untagged torrents in the pull job.
+///
+/// All given conditions must match. A rule without conditions is rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AutoTagRule {
+    /// Regular expression searched in the torrent name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Exact seedbox category.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Substring of the current tracker URL, e.g. its host.
+    #[serde(default)]
+    pub tracker: Option<String>,
+    /// Prefix of the save path.
+    #[serde(default)]
+    pub save_path: Option<String>,
+    /// Tracked tag to apply, defaults to the first configured tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Selection of torrents added before the earliest import date to adopt.
+///
+/// A torrent is selected if its hash is listed, or it was added within the
+/// date range. Dates are inclusive and in UTC.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AdoptConfig {
+    #[serde(default)]
+    pub hashes: Vec<String>,
+    #[serde(default)]
+    pub added_after: Option<NaiveDate>,
+    #[serde(default)]
+    pub added_before: Option<NaiveDate>,
+}
+
+impl AdoptConfig {
+    /// Whether nothing is selected.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.hashes.is_empty() && self.added_after.is_none() && self.added_before.is_none()
+    }
+
+    /// Whether a torrent added at the given time is selected.
+    #[must_use]
+    pub fn is_match(&self, hash: &str, added: DateTime<Utc>) -> bool {
+        if self.hashes.iter().any(|h| h.eq_ignore_ascii_case(hash)) {
+            return true;
+        }
+        if self.added_after.is_none() && self.added_before.is_none() {
+            return false;
+        }
+
+        let date = added.date_naive();
+        self.added_after.is_none_or(|after| date >= after)
+            && self.added_before.is_none_or(|before| date <= before)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertConfig {
+    /// Minutes a tracked torrent may stay stalled or errored before an alert is sent.
+    #[serde(default = "default_unhealthy_after")]
+    pub unhealthy_after: u64,
+    /// GiB of free space on a seedbox below which an alert is sent.
+    #[serde(default)]
+    pub free_space_below: Option<u64>,
+    /// Pause tracked downloads while free space is below `free_space_below`.
+    #[serde(default)]
+    pub pause_on_low_space: bool,
+}
+
+impl AlertConfig {
+    /// Free space threshold in bytes, if configured.
+    #[must_use]
+    pub const fn free_space_threshold(&self) -> Option<u64> {
+        match self.free_space_below {
+            Some(gib) => Some(gib.saturating_mul(1024 * 1024 * 1024)),
+            None => None,
+        }
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            unhealthy_after: DEFAULT_UNHEALTHY_AFTER,
+            free_space_below: None,
+            pause_on_low_space: false,
+        }
+    }
+}
+
+/// Seedbox-side actions run once the artifact of a torrent is archived.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArchiveConfig {
+    /// Remove the tracked tag from the torrent.
+    #[serde(default)]
+    pub remove_tag: bool,
+    /// Tag added to the torrent, e.g. `anipler-done`.
+    #[serde(default)]
+    pub add_tag: Option<String>,
+    /// Share ratio limit set on the torrent.
+    #[serde(default)]
+    pub ratio_limit: Option<f64>,
+    /// Seeding time limit set on the torrent, in minutes.
+    #[serde(default)]
+    pub seeding_time_limit: Option<u64>,
+    #[serde(default)]
+    pub delete: DeleteMode,
+}
+
+/// Whether and how archived torrents are deleted from the seedbox.
+///
+/// Deletion waits until `ratio_limit` or `seeding_time_limit` is reached, or
+/// happens right away if neither is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteMode {
+    #[default]
+    Keep,
+    /// Delete the torrent but keep its data.
+    Torrent,
+    /// Delete the torrent along with its data.
+    WithData,
+}
+
+impl ArchiveConfig {
+    /// Whether any post-archive action is configured.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.remove_tag
+            || self.add_tag.is_some()
+            || self.ratio_limit.is_some()
+            || self.seeding_time_limit.is_some()
+            || self.delete != DeleteMode::Keep
+    }
+}
+
+/// Handling policy for torrents carrying a tracked tag.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagPolicy {
+    /// Tag (or label, depending on the seedbox backend) to track.
+    pub name: String,
+    #[serde(default)]
+    pub transfer: TransferMode,
+    /// Subdirectory of the relay artifact storage for this tag.
+    #[serde(default)]
+    pub relay_subdir: Option<PathBuf>,
+    /// Key into `transfer.bandwidth_classes`.
+    #[serde(default)]
+    pub bandwidth_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+    /// Transfer with the scheduled transfer job.
+    #[default]
+    Auto,
+    /// Only transfer when requested by the user.
+    Manual,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -72,18 +318,107 @@ pub struct QBitConfig {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransmissionConfig {
+    /// Full URL of the RPC endpoint, usually ending with `/transmission/rpc`.
+    pub url: Url,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DelugeConfig {
+    /// Base URL of the Deluge Web UI, the JSON-RPC endpoint is `json` below it.
+    pub url: Url,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RTorrentConfig {
+    /// `scgi://host:port`, `scgi:///path/to/rpc.socket` or an HTTP XML-RPC endpoint.
+    pub url: Url,
+    /// Custom field marking anipler torrents, one of `custom1` to `custom5`.
+    #[serde(default = "default_rtorrent_tag_field")]
+    pub tag_field: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct SeedboxConfig {
+    /// Unique name, recorded on the tasks of this seedbox.
+    #[serde(default = "default_seedbox_name")]
+    pub name: String,
+    #[serde(default)]
+    pub backend: SeedboxBackend,
     pub ssh_host: String,
     pub ssh_key: PathBuf,
+    /// rsync bandwidth limit for this seedbox, overriding `transfer.speed_limit`.
+    #[serde(default)]
+    pub speed_limit: Option<u32>,
+    #[serde(default)]
+    pub qbit: Option<QBitConfig>,
+    #[serde(default)]
+    pub transmission: Option<TransmissionConfig>,
+    #[serde(default)]
+    pub deluge: Option<DelugeConfig>,
+    #[serde(default)]
+    pub rtorrent: Option<RTorrentConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct TransferConfig {
     #[serde(default)]
     pub dry_run: bool,
+    /// How files are transferred from seedboxes.
+    #[serde(default)]
+    pub backend: TransferBackend,
     #[serde(default)]
     pub speed_limit: Option<u32>,
+    /// Named rsync bandwidth limits referenced by tag policies.
+    #[serde(default)]
+    pub bandwidth_classes: HashMap<String, u32>,
+    /// Glob patterns of torrent files to transfer, everything if empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of torrent files to skip.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Transfer torrents again whose content changed on the seedbox after
+    /// their artifact was ready, instead of only alerting.
+    #[serde(default)]
+    pub retransfer_on_change: bool,
+    /// Maximum number of transfers running in parallel.
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Failed transfer attempts of a torrent before it needs a manual retry.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Minutes to wait after the first failed attempt, doubled after each
+    /// further failure.
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            backend: TransferBackend::default(),
+            speed_limit: None,
+            bandwidth_classes: HashMap::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            retransfer_on_change: false,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -113,6 +448,16 @@ pub struct PullerConfig {
     pub api_key: String,
     pub ssh_host: String,
     pub destination: PathBuf,
+    /// Destinations of artifacts by seedbox category, others go to `destination`.
+    #[serde(default)]
+    pub categories: Vec<CategoryDestination>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CategoryDestination {
+    /// Seedbox category, e.g. `TV` or `Movies`.
+    pub name: String,
+    pub destination: PathBuf,
 }
 
 #[derive(Clone)]
@@ -122,7 +467,7 @@ struct ConfigFile {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
-struct DaemonConfigOverrides {
+pub(crate) struct DaemonConfigOverrides {
     dry_run: Option<bool>,
     stateless: Option<bool>,
 }
@@ -157,6 +502,47 @@ impl DaemonConfig {
         !self.transfer.dry_run
     }
 
+    /// Names of tracked tags in order of precedence.
+    #[must_use]
+    pub fn tag_names(&self) -> Vec<String> {
+        self.tags.iter().map(|tag| tag.name.clone()).collect()
+    }
+
+    /// Policy of a tracked tag.
+    #[must_use]
+    pub fn tag_policy(&self, tag: &str) -> Option<&TagPolicy> {
+        self.tags.iter().find(|policy| policy.name == tag)
+    }
+
+    /// Tag applied to torrents uploaded without an explicit tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no tag is configured, which validation rules out.
+    #[must_use]
+    pub fn default_tag(&self) -> &str {
+        &self.tags.first().expect("validated non-empty").name
+    }
+
+    /// Configuration of a named seedbox.
+    #[must_use]
+    pub fn seedbox_config(&self, name: &str) -> Option<&SeedboxConfig> {
+        self.seedboxes.iter().find(|seedbox| seedbox.name == name)
+    }
+
+    /// rsync bandwidth limit for torrents with the given tag on the given seedbox.
+    ///
+    /// The bandwidth class of the tag takes precedence over the limit of the
+    /// seedbox, which takes precedence over `transfer.speed_limit`.
+    #[must_use]
+    pub fn speed_limit_for(&self, tag: &str, seedbox: &str) -> Option<u32> {
+        self.tag_policy(tag)
+            .and_then(|policy| policy.bandwidth_class.as_ref())
+            .and_then(|class| self.transfer.bandwidth_classes.get(class).copied())
+            .or_else(|| self.seedbox_config(seedbox)?.speed_limit)
+            .or(self.transfer.speed_limit)
+    }
+
     fn load_from_config_file(
         config_file: Option<ConfigFile>,
         env_source: Option<Environment>,
@@ -184,18 +570,262 @@ impl DaemonConfig {
 
     fn validate(mut self) -> Result<Self, ConfigLoadError> {
         self.storage_path = expand_path("storage_path", &self.storage_path)?;
-        self.seedbox.ssh_key = expand_path("seedbox.ssh_key", &self.seedbox.ssh_key)?;
-        if !validate_file_exists(&self.seedbox.ssh_key) {
-            return Err(ConfigLoadError::PathValidation {
-                field: "seedbox.ssh_key",
-                path: self.seedbox.ssh_key,
-            });
+        self.merge_legacy_seedbox()?;
+        if self.alerts.pause_on_low_space && self.alerts.free_space_below.is_none() {
+            return Err(ConfigLoadError::Config(
+                "alerts.pause_on_low_space requires alerts.free_space_below".to_string(),
+            ));
+        }
+        if self.transfer.max_concurrent == 0 {
+            return Err(ConfigLoadError::Config(
+                "transfer.max_concurrent must be at least 1".to_string(),
+            ));
+        }
+        if self.transfer.max_attempts == 0 {
+            return Err(ConfigLoadError::Config(
+                "transfer.max_attempts must be at least 1".to_string(),
+            ));
+        }
+        self.validate_seedboxes()?;
+        self.validate_tags()?;
+        FileFilter::from_config(&self.transfer)
+            .map_err(|e| ConfigLoadError::Config(format!("invalid transfer file rule: {e}")))?;
+        self.validate_auto_tag()?;
+        let mut names = HashSet::new();
+        for rule in &self.rss {
+            if !names.insert(rule.name.as_str()) {
+                return Err(ConfigLoadError::Config(format!(
+                    "rss rule {} is configured more than once",
+                    rule.name
+                )));
+            }
+            self.validate_rss_rule(rule)
+                .map_err(ConfigLoadError::Config)?;
         }
         Ok(self)
     }
 
+    /// Move the single `[seedbox]` of older configurations, along with the
+    /// top-level backend sections, to the front of `seedboxes`.
+    ///
+    /// Top-level backend sections left over, e.g. next to `[[seedboxes]]`,
+    /// would be silently ignored and are rejected instead.
+    fn merge_legacy_seedbox(&mut self) -> Result<(), ConfigLoadError> {
+        if let Some(mut seedbox) = self.seedbox.take() {
+            seedbox.qbit = seedbox.qbit.or_else(|| self.qbit.take());
+            seedbox.transmission = seedbox.transmission.or_else(|| self.transmission.take());
+            seedbox.deluge = seedbox.deluge.or_else(|| self.deluge.take());
+            seedbox.rtorrent = seedbox.rtorrent.or_else(|| self.rtorrent.take());
+            self.seedboxes.insert(0, seedbox);
+        }
+
+        let leftover = [
+            ("qbit", self.qbit.is_some()),
+            ("transmission", self.transmission.is_some()),
+            ("deluge", self.deluge.is_some()),
+            ("rtorrent", self.rtorrent.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(section, present)| present.then_some(section));
+        if let Some(section) = leftover {
+            return Err(ConfigLoadError::Config(format!(
+                "top-level [{section}] is only used by a single [seedbox], configure it as [seedboxes.{section}] instead"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_seedboxes(&mut self) -> Result<(), ConfigLoadError> {
+        if self.seedboxes.is_empty() {
+            return Err(ConfigLoadError::Config(
+                "at least one seedbox must be configured".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for seedbox in &mut self.seedboxes {
+            if !names.insert(seedbox.name.clone()) {
+                return Err(ConfigLoadError::Config(format!(
+                    "seedbox {} is configured more than once",
+                    seedbox.name
+                )));
+            }
+
+            seedbox.ssh_key = expand_path("seedbox.ssh_key", &seedbox.ssh_key)?;
+            if !validate_file_exists(&seedbox.ssh_key) {
+                return Err(ConfigLoadError::PathValidation {
+                    field: "seedbox.ssh_key",
+                    path: seedbox.ssh_key.clone(),
+                });
+            }
+
+            let (section, present) = match seedbox.backend {
+                SeedboxBackend::QBit => ("qbit", seedbox.qbit.is_some()),
+                SeedboxBackend::Transmission => ("transmission", seedbox.transmission.is_some()),
+                SeedboxBackend::Deluge => ("deluge", seedbox.deluge.is_some()),
+                SeedboxBackend::RTorrent => ("rtorrent", seedbox.rtorrent.is_some()),
+            };
+            if !present {
+                return Err(ConfigLoadError::MissingSection {
+                    seedbox: seedbox.name.clone(),
+                    section,
+                });
+            }
+
+            if self.archive.is_enabled() && seedbox.backend != SeedboxBackend::QBit {
+                return Err(ConfigLoadError::Config(
+                    "archive actions are only supported by the qbit seedbox backend".to_string(),
+                ));
+            }
+            if self.alerts.pause_on_low_space && seedbox.backend != SeedboxBackend::QBit {
+                return Err(ConfigLoadError::Config(
+                    "alerts.pause_on_low_space is only supported by the qbit seedbox backend"
+                        .to_string(),
+                ));
+            }
+            // Deluge lowercases labels, so mixed-case tags would never match.
+            if seedbox.backend == SeedboxBackend::Deluge
+                && let Some(tag) = self
+                    .tags
+                    .iter()
+                    .find(|tag| tag.name != tag.name.to_lowercase())
+            {
+                return Err(ConfigLoadError::Config(format!(
+                    "tag {} must be lowercase, deluge seedbox {} lowercases labels",
+                    tag.name, seedbox.name
+                )));
+            }
+            if self.transfer.backend == TransferBackend::Sftp {
+                crate::sftp::parse_ssh_host(&seedbox.ssh_host)
+                    .map_err(|e| ConfigLoadError::Config(e.to_string()))?;
+            }
+            if let Some(rtorrent) = &seedbox.rtorrent
+                && !RTORRENT_TAG_FIELDS.contains(&rtorrent.tag_field.as_str())
+            {
+                return Err(ConfigLoadError::Config(format!(
+                    "rtorrent.tag_field must be one of {}",
+                    RTORRENT_TAG_FIELDS.join(", ")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_tags(&self) -> Result<(), ConfigLoadError> {
+        if self.tags.is_empty() {
+            return Err(ConfigLoadError::Config(
+                "at least one tag must be tracked".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for tag in &self.tags {
+            if !names.insert(tag.name.as_str()) {
+                return Err(ConfigLoadError::Config(format!(
+                    "tag {} is configured more than once",
+                    tag.name
+                )));
+            }
+            if let Some(class) = &tag.bandwidth_class
+                && !self.transfer.bandwidth_classes.contains_key(class)
+            {
+                return Err(ConfigLoadError::Config(format!(
+                    "tag {} refers to unknown bandwidth class {class}",
+                    tag.name
+                )));
+            }
+            if let Some(subdir) = &tag.relay_subdir
+                && !subdir
+                    .components()
+                    .all(|c| matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(ConfigLoadError::Config(format!(
+                    "relay_subdir of tag {} must be a plain relative path",
+                    tag.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seedbox and tracked tag of an RSS rule, with defaults applied.
+    #[must_use]
+    pub fn rss_rule_target<'a>(&'a self, rule: &'a RssRule) -> (&'a str, &'a str) {
+        let seedbox = rule
+            .seedbox
+            .as_deref()
+            .unwrap_or(self.seedboxes[0].name.as_str());
+        let tag = rule.tag.as_deref().unwrap_or_else(|| self.default_tag());
+        (seedbox, tag)
+    }
+
+    /// Check that an RSS rule targets a qBittorrent seedbox and applies a tracked tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first problem of the rule.
+    pub fn validate_rss_rule(&self, rule: &RssRule) -> Result<(), String> {
+        if rule.name.is_empty() || rule.feeds.is_empty() {
+            return Err(format!(
+                "rss rule {:?} needs a name and at least one feed",
+                rule.name
+            ));
+        }
+        let seedbox = match &rule.seedbox {
+            Some(name) => self.seedbox_config(name).ok_or_else(|| {
+                format!("rss rule {} refers to unknown seedbox {name}", rule.name)
+            })?,
+            None => &self.seedboxes[0],
+        };
+        if seedbox.backend != SeedboxBackend::QBit {
+            return Err("rss rules are only supported by the qbit seedbox backend".to_string());
+        }
+        if let Some(tag) = &rule.tag
+            && self.tag_policy(tag).is_none()
+        {
+            return Err(format!(
+                "rss rule {} applies untracked tag {tag}",
+                rule.name
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_auto_tag(&self) -> Result<(), ConfigLoadError> {
+        if self.auto_tag.is_empty() {
+            return Ok(());
+        }
+
+        if self
+            .seedboxes
+            .iter()
+            .any(|seedbox| seedbox.backend != SeedboxBackend::QBit)
+        {
+            return Err(ConfigLoadError::Config(
+                "auto_tag rules are only supported by the qbit seedbox backend".to_string(),
+            ));
+        }
+        for rule in &self.auto_tag {
+            if let Some(tag) = &rule.tag
+                && self.tag_policy(tag).is_none()
+            {
+                return Err(ConfigLoadError::Config(format!(
+                    "auto_tag rule applies untracked tag {tag}"
+                )));
+            }
+        }
+        AutoTagger::from_config(&self.auto_tag, self.default_tag())
+            .map_err(|e| ConfigLoadError::Config(format!("invalid auto_tag rule: {e}")))?;
+
+        Ok(())
+    }
+
     #[cfg(test)]
-    fn load_from_toml(
+    pub(crate) fn load_from_toml(
         toml: &str,
         env_source: Option<::config::Map<String, String>>,
         overrides: DaemonConfigOverrides,
@@ -225,6 +855,24 @@ impl TransferConfig {
     pub fn is_dry_run(&self) -> bool {
         self.dry_run
     }
+
+    /// Delay before the next transfer attempt after `attempts` failures, or
+    /// `None` once `max_attempts` is reached.
+    #[must_use]
+    pub fn retry_delay(&self, attempts: u32) -> Option<chrono::TimeDelta> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let exponent = attempts.saturating_sub(1).min(u64::BITS - 1);
+        let minutes = self
+            .retry_backoff
+            .saturating_mul(1 << exponent)
+            .min(MAX_RETRY_DELAY);
+        Some(chrono::TimeDelta::minutes(
+            i64::try_from(minutes).unwrap_or(i64::MAX),
+        ))
+    }
 }
 
 impl PullerConfig {
@@ -288,6 +936,9 @@ impl PullerConfig {
 
     fn validate(mut self) -> Result<Self, ConfigLoadError> {
         self.destination = expand_path("destination", &self.destination)?;
+        for category in &mut self.categories {
+            category.destination = expand_path("categories.destination", &category.destination)?;
+        }
         Ok(self)
     }
 
@@ -440,6 +1091,39 @@ fn expand_path(field: &'static str, path: &Path) -> Result<PathBuf, ConfigLoadEr
         })
 }
 
+fn default_tags() -> Vec<TagPolicy> {
+    vec![TagPolicy {
+        name: DEFAULT_TORRENT_TAG.to_string(),
+        transfer: TransferMode::default(),
+        relay_subdir: None,
+        bandwidth_class: None,
+    }]
+}
+
+fn default_seedbox_name() -> String {
+    DEFAULT_SEEDBOX_NAME.to_string()
+}
+
+const fn default_max_concurrent() -> usize {
+    DEFAULT_MAX_CONCURRENT
+}
+
+const fn default_max_attempts() -> u32 {
+    DEFAULT_MAX_ATTEMPTS
+}
+
+const fn default_retry_backoff() -> u64 {
+    DEFAULT_RETRY_BACKOFF
+}
+
+const fn default_unhealthy_after() -> u64 {
+    DEFAULT_UNHEALTHY_AFTER
+}
+
+fn default_rtorrent_tag_field() -> String {
+    DEFAULT_RTORRENT_TAG_FIELD.to_string()
+}
+
 fn validate_file_exists(path: &Path) -> bool {
     fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
 }
@@ -500,7 +1184,11 @@ key = "api-key"
         assert!(!config.stateless);
         assert!(!config.transfer.is_dry_run());
         assert!(config.transfers_enabled());
-        assert_eq!(config.seedbox.ssh_key, ssh_key);
+        assert_eq!(config.seedboxes[0].ssh_key, ssh_key);
+        assert_eq!(config.seedboxes[0].name, DEFAULT_SEEDBOX_NAME);
+        assert!(config.seedboxes[0].qbit.is_some());
+        assert_eq!(config.tag_names(), vec![DEFAULT_TORRENT_TAG.to_string()]);
+        assert_eq!(config.default_tag(), DEFAULT_TORRENT_TAG);
     }
 
     #[test]
@@ -544,7 +1232,7 @@ key = "api-key"
         )
         .unwrap();
 
-        assert_eq!(config.seedbox.ssh_host, "env-seedbox.example");
+        assert_eq!(config.seedboxes[0].ssh_host, "env-seedbox.example");
     }
 
     #[test]
@@ -607,7 +1295,367 @@ key = "api-key"
         )
         .unwrap();
 
-        assert_eq!(config.seedbox.ssh_key, symlink_key);
+        assert_eq!(config.seedboxes[0].ssh_key, symlink_key);
+    }
+
+    #[test]
+    fn daemon_config_selects_transmission_backend() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = minimal_daemon_toml(&ssh_key)
+            .replace("[seedbox]\n", "[seedbox]\nbackend = \"transmission\"\n");
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigLoadError::MissingSection {
+                section: "transmission",
+                ..
+            }
+        ));
+
+        let toml =
+            format!("{toml}\n[transmission]\nurl = \"http://localhost:9091/transmission/rpc\"\n");
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(config.seedboxes[0].backend, SeedboxBackend::Transmission);
+        assert!(
+            config.seedboxes[0]
+                .transmission
+                .as_ref()
+                .unwrap()
+                .username
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn daemon_config_requires_lowercase_tags_for_deluge() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            "{}\n[deluge]\nurl = \"http://localhost:8112\"\npassword = \"deluge\"\n",
+            minimal_daemon_toml(&ssh_key)
+                .replace("[seedbox]\n", "[seedbox]\nbackend = \"deluge\"\n")
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(config.seedboxes[0].backend, SeedboxBackend::Deluge);
+
+        let toml = format!("{toml}\n[[tags]]\nname = \"Anime\"\n");
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(reason) if reason.contains("Anime")));
+    }
+
+    #[test]
+    fn daemon_config_loads_tag_policies() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            r#"{}
+[transfer]
+speed_limit = 2048
+
+[transfer.bandwidth_classes]
+slow = 256
+
+[[tags]]
+name = "anime"
+
+[[tags]]
+name = "anime-archive"
+transfer = "manual"
+relay_subdir = "archive"
+bandwidth_class = "slow"
+"#,
+            minimal_daemon_toml(&ssh_key)
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+
+        assert_eq!(config.tag_names(), vec!["anime", "anime-archive"]);
+        assert_eq!(config.default_tag(), "anime");
+        let archive = config.tag_policy("anime-archive").unwrap();
+        assert_eq!(archive.transfer, TransferMode::Manual);
+        assert_eq!(archive.relay_subdir.as_deref(), Some(Path::new("archive")));
+        assert_eq!(
+            config.speed_limit_for("anime-archive", DEFAULT_SEEDBOX_NAME),
+            Some(256)
+        );
+        assert_eq!(
+            config.speed_limit_for("anime", DEFAULT_SEEDBOX_NAME),
+            Some(2048)
+        );
+
+        let toml = toml.replace("bandwidth_class = \"slow\"", "bandwidth_class = \"fast\"");
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+    }
+
+    #[test]
+    fn daemon_config_loads_named_seedboxes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            r#"
+storage_path = "/tmp/anipler"
+
+[transfer]
+speed_limit = 2048
+
+[[seedboxes]]
+name = "box-a"
+ssh_host = "a.example"
+ssh_key = "{key}"
+speed_limit = 512
+
+[seedboxes.qbit]
+url = "http://a.example:8080"
+username = "admin"
+password = "password"
+
+[[seedboxes]]
+name = "box-b"
+backend = "transmission"
+ssh_host = "b.example"
+ssh_key = "{key}"
+
+[seedboxes.transmission]
+url = "http://b.example:9091/transmission/rpc"
+
+[telegram]
+bot_token = "bot-token"
+chat_id = 42
+
+[api]
+key = "api-key"
+"#,
+            key = ssh_key.to_string_lossy()
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+
+        assert_eq!(config.seedboxes.len(), 2);
+        assert_eq!(
+            config.seedbox_config("box-b").unwrap().ssh_host,
+            "b.example"
+        );
+        assert_eq!(
+            config.seedbox_config("box-b").unwrap().backend,
+            SeedboxBackend::Transmission
+        );
+        assert_eq!(
+            config.speed_limit_for(DEFAULT_TORRENT_TAG, "box-a"),
+            Some(512)
+        );
+        assert_eq!(
+            config.speed_limit_for(DEFAULT_TORRENT_TAG, "box-b"),
+            Some(2048)
+        );
+
+        // Top-level backend sections are only merged into a single `[seedbox]`.
+        let leftover = toml.replace(
+            "[telegram]",
+            "[qbit]\nurl = \"http://c.example:8080\"\nusername = \"admin\"\npassword = \"password\"\n\n[telegram]",
+        );
+        let err = DaemonConfig::load_from_toml(&leftover, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(reason) if reason.contains("[qbit]")));
+
+        let toml = toml.replace("name = \"box-b\"", "name = \"box-a\"");
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+    }
+
+    #[test]
+    fn daemon_config_loads_archive_actions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let config = DaemonConfig::load_from_toml(
+            &minimal_daemon_toml(&ssh_key),
+            None,
+            DaemonConfigOverrides::default(),
+        )
+        .unwrap();
+        assert!(!config.archive.is_enabled());
+
+        let toml = format!(
+            r#"{}
+[archive]
+remove_tag = true
+add_tag = "anipler-done"
+ratio_limit = 2.0
+delete = "with_data"
+"#,
+            minimal_daemon_toml(&ssh_key)
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert!(config.archive.is_enabled());
+        assert_eq!(config.archive.add_tag.as_deref(), Some("anipler-done"));
+        assert_eq!(config.archive.delete, DeleteMode::WithData);
+
+        let toml = format!(
+            "{}\n[transmission]\nurl = \"http://localhost:9091/transmission/rpc\"\n",
+            toml.replace("[seedbox]\n", "[seedbox]\nbackend = \"transmission\"\n")
+        );
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+    }
+
+    #[test]
+    fn daemon_config_requires_threshold_for_low_space_pause() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            "{}\n[alerts]\npause_on_low_space = true\n",
+            minimal_daemon_toml(&ssh_key)
+        );
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+
+        let toml = toml.replace("[alerts]\n", "[alerts]\nfree_space_below = 50\n");
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(
+            config.alerts.free_space_threshold(),
+            Some(50 * 1024 * 1024 * 1024)
+        );
+    }
+
+    #[test]
+    fn daemon_config_limits_parallel_transfers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = minimal_daemon_toml(&ssh_key);
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(config.transfer.max_concurrent, 1);
+
+        let toml = format!("{toml}\n[transfer]\nmax_concurrent = 0\n");
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+    }
+
+    #[test]
+    fn retry_delay_backs_off_exponentially_until_max_attempts() {
+        let config = TransferConfig {
+            max_attempts: 4,
+            retry_backoff: 30,
+            ..TransferConfig::default()
+        };
+
+        assert_eq!(config.retry_delay(1), Some(chrono::TimeDelta::minutes(30)));
+        assert_eq!(config.retry_delay(3), Some(chrono::TimeDelta::minutes(120)));
+        assert_eq!(config.retry_delay(4), None);
+
+        let config = TransferConfig {
+            max_attempts: 100,
+            ..config
+        };
+        assert_eq!(
+            config.retry_delay(80),
+            Some(chrono::TimeDelta::minutes(24 * 60))
+        );
+    }
+
+    #[test]
+    fn daemon_config_validates_sftp_hosts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            "{}\n[transfer]\nbackend = \"sftp\"\n",
+            minimal_daemon_toml(&ssh_key).replace("seedbox.example", "user@seedbox.example:ssh")
+        );
+        let err = DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default())
+            .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+
+        let toml = toml.replace(":ssh", ":2222");
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(config.transfer.backend, TransferBackend::Sftp);
+    }
+
+    #[test]
+    fn daemon_config_loads_auto_tag_rules() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            "{}\n[[auto_tag]]\nname = \"(?i)batch\"\ncategory = \"anime\"\n",
+            minimal_daemon_toml(&ssh_key)
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        assert_eq!(config.auto_tag.len(), 1);
+        assert_eq!(config.auto_tag[0].category.as_deref(), Some("anime"));
+
+        let err = DaemonConfig::load_from_toml(
+            &format!("{toml}tag = \"untracked\"\n"),
+            None,
+            DaemonConfigOverrides::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+
+        let err = DaemonConfig::load_from_toml(
+            &toml.replace("(?i)batch", "(unclosed"),
+            None,
+            DaemonConfigOverrides::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigLoadError::Config(_)));
+    }
+
+    #[test]
+    fn daemon_config_loads_adopt_selection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ssh_key = temp_dir.path().join("id_ed25519");
+        fs::write(&ssh_key, "test-key").unwrap();
+
+        let toml = format!(
+            "{}\n[adopt]\nhashes = [\"ABCD\"]\nadded_after = \"2025-01-01\"\nadded_before = \"2025-03-31\"\n",
+            minimal_daemon_toml(&ssh_key)
+        );
+        let config =
+            DaemonConfig::load_from_toml(&toml, None, DaemonConfigOverrides::default()).unwrap();
+        let adopt = &config.adopt;
+        let at = |date: &str| {
+            DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        assert!(!adopt.is_empty());
+        assert!(adopt.is_match("abcd", at("2020-01-01T00:00:00Z")));
+        assert!(adopt.is_match("ffff", at("2025-01-01T00:00:00Z")));
+        assert!(adopt.is_match("ffff", at("2025-03-31T23:59:59Z")));
+        assert!(!adopt.is_match("ffff", at("2025-04-01T00:00:00Z")));
+        assert!(!AdoptConfig::default().is_match("ffff", at("2025-02-01T00:00:00Z")));
     }
 
     #[test]
@@ -629,5 +1677,35 @@ destination = "{}"
         assert_eq!(config.api_key, "api-key");
         assert_eq!(config.ssh_host, "relay.example");
         assert_eq!(config.destination, destination);
+        assert!(config.categories.is_empty());
+    }
+
+    #[test]
+    fn puller_config_loads_category_destinations() {
+        let config = PullerConfig::load_from_toml(
+            r#"
+api_url = "http://localhost:8080"
+api_key = "api-key"
+ssh_host = "relay.example"
+destination = "/srv/downloads"
+
+[[categories]]
+name = "TV"
+destination = "/srv/media/tv"
+
+[[categories]]
+name = "Movies"
+destination = "/srv/media/movies"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.categories.len(), 2);
+        assert_eq!(config.categories[0].name, "TV");
+        assert_eq!(
+            config.categories[0].destination,
+            PathBuf::from("/srv/media/tv")
+        );
+        assert_eq!(config.categories[1].name, "Movies");
     }
 }
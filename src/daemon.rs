@@ -1,27 +1,35 @@
-use std::sync::Arc;
+use std::{future, sync::Arc, time::Duration};
 
+use futures::{StreamExt, stream};
+use qbit_rs::model::TorrentSource;
 use tokio::task::JoinHandle;
 use tokio_cron_scheduler::{JobScheduler, JobSchedulerError};
 use tokio_util::sync::CancellationToken;
 use tracing::instrument;
 
 use crate::{
+    adopt,
     api::ApiServer,
-    bot::{BotCommand, ReportTorrentInfo, TelegramBot},
-    config::DaemonConfig,
-    qbit::QBitSeedbox,
+    autotag::AutoTagger,
+    bot::{BotCommand, ReportTorrentInfo, RssCommand, TelegramBot},
+    config::{AdoptConfig, DaemonConfig, DeleteMode, RssRule, TransferMode},
+    filter::FileFilter,
     rsync::{RsyncTransferSession, RsyncTransmitter, RsyncTransmitterError, TransferGuard},
+    seedbox::{Seedbox, SeedboxSet},
     storage::{StorageManager, StorageManagerError, TaskStatus},
-    task::TransferTaskInfo,
+    task::{PendingCleanup, TorrentTaskInfo, TransferTaskInfo},
+    upload::{self, UploadError},
 };
 
 pub struct AniplerDaemon {
     api: ApiServer,
+    auto_tagger: AutoTagger,
     bot: TelegramBot,
     config: DaemonConfig,
-    seedbox: QBitSeedbox,
+    file_filter: FileFilter,
+    seedboxes: SeedboxSet,
     store: Arc<StorageManager>,
-    transmitter: RsyncTransmitter,
+    transmitter: Arc<RsyncTransmitter>,
 }
 
 impl AniplerDaemon {
@@ -33,27 +41,39 @@ impl AniplerDaemon {
     pub async fn from_config(config: DaemonConfig) -> anyhow::Result<Arc<Self>> {
         tracing::info!("Initializing Anipler daemon");
 
-        tracing::debug!("Initializing seedbox connection");
-        let seedbox = QBitSeedbox::from_config(&config);
+        tracing::debug!(
+            count = config.seedboxes.len(),
+            "Initializing seedbox connections"
+        );
+        let seedboxes = SeedboxSet::from_config(&config)?;
 
         tracing::debug!("Initializing storage manager");
         let store = StorageManager::from_config(&config).await?;
         let store_arc = Arc::new(store);
 
         tracing::debug!("Initializing rsync transmitter");
-        let transmitter = RsyncTransmitter::from_config(&config);
+        let transmitter = Arc::new(RsyncTransmitter::from_config(&config));
+        let file_filter = FileFilter::from_config(&config.transfer)?;
+        let auto_tagger = AutoTagger::from_config(&config.auto_tag, config.default_tag())?;
 
         tracing::debug!("Initializing Telegram bot");
         let bot = TelegramBot::from_config(&config);
 
         tracing::debug!("Initializing API server");
-        let api = ApiServer::from_config(&config, store_arc.clone());
+        let api = ApiServer::from_config(
+            &config,
+            store_arc.clone(),
+            seedboxes.clone(),
+            transmitter.clone(),
+        );
 
         let daemon = Self {
             api,
+            auto_tagger,
             bot,
             config,
-            seedbox,
+            file_filter,
+            seedboxes,
             store: store_arc,
             transmitter,
         };
@@ -75,6 +95,14 @@ impl AniplerDaemon {
 
         self.run_pull_job().await;
 
+        self.sync_rss_rules()
+            .await
+            .unwrap_or_else(|e| tracing::error!(error = ?e, "Failed to sync RSS rules"));
+
+        if !self.config.adopt.is_empty() {
+            self.run_adopt_job(&self.config.adopt).await;
+        }
+
         self.bot.run().await?;
 
         tracing::info!("Daemon main loop started");
@@ -202,12 +230,32 @@ impl AniplerDaemon {
             "Starting torrent information pull from seedbox"
         );
 
-        self.update_status()
+        for (name, seedbox) in self.seedboxes.iter() {
+            self.update_status(name, seedbox.as_ref()).await.unwrap_or_else(
+                |e| tracing::error!(error = ?e, seedbox = %name, "Failed to pull torrents information"),
+            );
+            self.auto_tag(name, seedbox.as_ref()).await.unwrap_or_else(
+                |e| tracing::error!(error = ?e, seedbox = %name, "Failed to apply auto-tagging rules"),
+            );
+            self.check_free_space(name, seedbox.as_ref()).await.unwrap_or_else(
+                |e| tracing::error!(error = ?e, seedbox = %name, "Failed to check seedbox free space"),
+            );
+        }
+
+        self.alert_unhealthy()
             .await
-            .unwrap_or_else(|e| tracing::error!(error = ?e, "Failed to pull torrents information"));
+            .unwrap_or_else(|e| tracing::error!(error = ?e, "Failed to check unhealthy torrents"));
+
+        self.cleanup_archived().await.unwrap_or_else(
+            |e| tracing::error!(error = ?e, "Failed to clean up archived torrents"),
+        );
     }
 
     /// Wrapper around transfer jobs with errors caught and logged.
+    ///
+    /// Torrents whose tag is set to manual transfer, and torrents waiting for
+    /// the backoff of a failed attempt, are skipped. They are only transferred
+    /// when requested by hash, see [`Self::run_priority_transfer`].
     #[instrument(skip(self))]
     // False positive on `session`, which will be consumed in `release`.
     #[allow(clippy::significant_drop_tightening)]
@@ -221,18 +269,33 @@ impl AniplerDaemon {
                 return;
             }
         };
+        let deferred = match self.store.list_deferred_transfers(chrono::Utc::now()).await {
+            Ok(deferred) => deferred,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to list deferred transfers");
+                return;
+            }
+        };
         let transfer_tasks = ready_torrents
             .iter()
-            .map(|torrent| TransferTaskInfo {
-                hash: torrent.hash.clone(),
-                source: torrent.content_path.clone(),
-                dest: self
-                    .store
-                    .artifact_storage_path(&torrent.hash)
-                    .to_string_lossy()
-                    .to_string(),
-                name: torrent.name.clone(),
+            .filter(|torrent| {
+                let deferred = deferred.contains(&torrent.hash);
+                if deferred {
+                    tracing::debug!(torrent = %torrent.name, hash = %torrent.hash, "Skipping torrent waiting for transfer retry");
+                }
+                !deferred
+            })
+            .filter(|torrent| {
+                let manual = self
+                    .config
+                    .tag_policy(&torrent.tag)
+                    .is_some_and(|policy| policy.transfer == TransferMode::Manual);
+                if manual {
+                    tracing::debug!(torrent = %torrent.name, hash = %torrent.hash, tag = %torrent.tag, "Skipping torrent with manual transfer policy");
+                }
+                !manual
             })
+            .map(|torrent| self.transfer_task_info(torrent))
             .collect::<Vec<_>>();
         let total_count = transfer_tasks.len();
         tracing::info!(count = total_count, "Found ready torrents for transfer");
@@ -252,22 +315,192 @@ impl AniplerDaemon {
             }
         };
 
-        let mut transferred = 0;
-        for task in session.tasks() {
-            match self.transfer_task(&session, task).await {
-                Ok(true) => {
-                    transferred += 1;
-                }
-                Ok(false) => {}
-                Err(e) => {
-                    tracing::error!(error = ?e, "Transfer task failed");
-                    self.bot.notify_transfer_failure(task, &e.to_string()).await;
+        let transferred = self.run_transfer_session(&session).await;
+        session.release().await;
+
+        tracing::info!(count = transferred, "Transfer job completed successfully");
+    }
+
+    /// Transfer ready torrents matching the given hash prefixes next, ahead of
+    /// the remaining tasks of a running transfer job.
+    ///
+    /// Manual transfer policies and retry backoff are ignored, as the torrents
+    /// are explicitly requested. Prefixes matching no or several ready torrents are reported
+    /// back to the user.
+    #[instrument(skip(self))]
+    pub async fn run_priority_transfer(&self, prefixes: &[String]) {
+        tracing::info!("Starting priority transfer");
+
+        let ready_torrents = match self.store.list_ready_torrents().await {
+            Ok(ready_torrents) => ready_torrents,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to list ready torrents");
+                return;
+            }
+        };
+
+        let mut transfer_tasks: Vec<TransferTaskInfo> = Vec::new();
+        let mut unmatched = Vec::new();
+        for prefix in prefixes {
+            let prefix = prefix.to_lowercase();
+            let mut matches = ready_torrents
+                .iter()
+                .filter(|torrent| torrent.hash.starts_with(&prefix));
+            match (matches.next(), matches.next()) {
+                (Some(torrent), None) => {
+                    if !transfer_tasks.iter().any(|task| task.hash == torrent.hash) {
+                        transfer_tasks.push(self.transfer_task_info(torrent));
+                    }
                 }
+                _ => unmatched.push(prefix),
             }
         }
+        self.bot
+            .notify_priority_transfer(&transfer_tasks, &unmatched)
+            .await;
+        if transfer_tasks.is_empty() {
+            return;
+        }
+
+        let session = self.transmitter.priority_session(transfer_tasks).await;
+        let transferred = self.run_transfer_session(&session).await;
         session.release().await;
 
-        tracing::info!(count = transferred, "Transfer job completed successfully");
+        tracing::info!(count = transferred, "Priority transfer completed");
+    }
+
+    /// Cancel the ongoing transfer matching a hash prefix on user request.
+    ///
+    /// The task stays ready but is held back from transfer jobs until it is
+    /// requested again by hash, see [`Self::run_priority_transfer`].
+    #[instrument(skip(self))]
+    pub async fn run_cancel_transfer(&self, prefix: &str) {
+        let result = self.transmitter.cancel(prefix).await;
+        if let Err(e) = &result {
+            tracing::warn!(error = %e, "Failed to cancel transfer");
+        }
+        self.bot
+            .notify_cancel_requested(result.map_err(|e| e.to_string()))
+            .await;
+    }
+
+    /// Build the transfer task of a ready torrent.
+    fn transfer_task_info(&self, torrent: &TorrentTaskInfo) -> TransferTaskInfo {
+        TransferTaskInfo {
+            hash: torrent.hash.clone(),
+            source: torrent.content_path.clone(),
+            dest: self
+                .store
+                .artifact_storage_path(&torrent.hash, &torrent.tag, torrent.category.as_deref())
+                .to_string_lossy()
+                .to_string(),
+            name: torrent.name.clone(),
+            tag: torrent.tag.clone(),
+            category: torrent.category.clone(),
+            speed_limit: self.config.speed_limit_for(&torrent.tag, &torrent.seedbox),
+            seedbox: torrent.seedbox.clone(),
+            save_path: torrent.save_path.clone(),
+        }
+    }
+
+    /// Run all tasks of a session, up to `transfer.max_concurrent` at a time.
+    ///
+    /// Returns the number of transferred torrents. Failures are reported to
+    /// the user.
+    async fn run_transfer_session(&self, session: &RsyncTransferSession<'_>) -> usize {
+        // Create the futures up front, a stream holding the borrowing closure
+        // would not be `Send`.
+        let transfers = session
+            .tasks()
+            .iter()
+            .map(|task| self.run_session_task(session, task))
+            .collect::<Vec<_>>();
+
+        stream::iter(transfers)
+            .buffer_unordered(self.config.transfer.max_concurrent)
+            .fold(0, |count, transferred| future::ready(count + transferred))
+            .await
+    }
+
+    /// Run a single task of a session, returning 1 if it was transferred.
+    async fn run_session_task(
+        &self,
+        session: &RsyncTransferSession<'_>,
+        task: &TransferTaskInfo,
+    ) -> usize {
+        match self.transfer_task(session, task).await {
+            Ok(transferred) => usize::from(transferred),
+            Err(AniplerDaemonError::RsyncTransfer(RsyncTransmitterError::Cancelled { .. })) => {
+                tracing::info!(torrent = %task.name, hash = %task.hash, "Transfer cancelled, holding torrent until requested");
+                self.store
+                    .hold_transfer(&task.hash)
+                    .await
+                    .unwrap_or_else(|e| tracing::error!(error = ?e, torrent = %task.name, hash = %task.hash, "Failed to hold cancelled transfer"));
+                self.bot.notify_transfer_cancelled(task).await;
+                0
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "Transfer task failed");
+                self.record_transfer_failure(task, &e).await;
+                0
+            }
+        }
+    }
+
+    /// Record a failed transfer attempt, backing off or marking the task failed
+    /// once `transfer.max_attempts` is reached, and notify the user.
+    ///
+    /// Permanent failures, which retrying would not fix, mark the task failed
+    /// right away.
+    async fn record_transfer_failure(&self, task: &TransferTaskInfo, error: &AniplerDaemonError) {
+        let reason = error.to_string();
+        let transient = error.is_transient();
+        let failure = self
+            .store
+            .record_transfer_failure(&task.hash, &reason, |attempts| {
+                if !transient {
+                    return None;
+                }
+                self.config
+                    .transfer
+                    .retry_delay(attempts)
+                    .map(|delay| chrono::Utc::now() + delay)
+            })
+            .await
+            .inspect_err(|e| tracing::warn!(error = ?e, torrent = %task.name, hash = %task.hash, "Failed to record transfer failure"))
+            .ok();
+        if let Some(failure) = &failure {
+            match failure.retry_at {
+                Some(retry_at) => {
+                    tracing::info!(torrent = %task.name, hash = %task.hash, attempts = failure.attempts, retry_at = %retry_at, "Deferring transfer retry")
+                }
+                None => {
+                    tracing::warn!(torrent = %task.name, hash = %task.hash, attempts = failure.attempts, transient = transient, "Transfer failed, waiting for manual retry")
+                }
+            }
+        }
+
+        self.bot
+            .notify_transfer_failure(task, &reason, failure.as_ref())
+            .await;
+    }
+
+    /// Move failed tasks matching a hash prefix, or all of them, back to ready
+    /// on user request and transfer them.
+    #[instrument(skip(self))]
+    pub async fn run_retry_job(&self, prefix: Option<&str>) {
+        match self.store.retry_failed_tasks(prefix).await {
+            Ok(tasks) if tasks.is_empty() => {
+                tracing::info!("No failed transfer to retry");
+                self.bot.notify_nothing_to_retry(prefix).await;
+            }
+            Ok(tasks) => {
+                tracing::info!(count = tasks.len(), "Retrying failed transfers");
+                let hashes = tasks.into_iter().map(|task| task.hash).collect::<Vec<_>>();
+                self.run_priority_transfer(&hashes).await;
+            }
+            Err(e) => tracing::error!(error = ?e, "Failed to retry failed transfers"),
+        }
     }
 
     /// Execute one transfer task inside per-hash transfer guard.
@@ -319,34 +552,235 @@ impl AniplerDaemon {
     ) -> Result<(), AniplerDaemonError> {
         let hash = &task.hash;
 
-        self.store.prepare_artifact_storage(hash).await?;
+        self.store
+            .prepare_artifact_storage(hash, &task.tag, task.category.as_deref())
+            .await?;
+
+        let manifest = self.transfer_manifest(task).await?;
 
         // Transmitter handles dry-run execution internally.
-        transfer_guard.transfer(task).await?;
+        transfer_guard.transfer(task, manifest.as_deref()).await?;
 
         if self.config.transfers_enabled() {
+            if let Some(files) = &manifest {
+                self.store.record_manifest(hash, files).await?;
+            }
             self.store.mark_artifact_ready(hash).await?;
         }
 
         Ok(())
     }
 
+    /// Select files of a torrent according to `transfer.include` and `transfer.exclude`.
+    ///
+    /// Returns `None` if the torrent should be transferred as a whole.
+    async fn transfer_manifest(
+        &self,
+        task: &TransferTaskInfo,
+    ) -> Result<Option<Vec<String>>, AniplerDaemonError> {
+        if !self.file_filter.is_active() {
+            return Ok(None);
+        }
+
+        let Some(files) = self
+            .seedbox(&task.seedbox)?
+            .torrent_files(&task.hash)
+            .await
+            .map_err(AniplerDaemonError::Seedbox)?
+        else {
+            tracing::warn!(torrent = %task.name, hash = %task.hash, "Seedbox can not list torrent files, transferring whole torrent");
+            return Ok(None);
+        };
+
+        let total = files.len();
+        let selected = self.file_filter.select(files);
+        tracing::info!(torrent = %task.name, hash = %task.hash, selected = selected.len(), total = total, "Selected files for transfer");
+
+        if selected.is_empty() {
+            return Err(AniplerDaemonError::EmptyManifest);
+        }
+
+        Ok(Some(selected))
+    }
+
+    /// Adopt selected torrents added before the earliest import date and
+    /// report the result, with errors caught and logged.
+    #[instrument(skip(self))]
+    pub async fn run_adopt_job(&self, selection: &AdoptConfig) {
+        match adopt::adopt_torrents(&self.store, &self.seedboxes, selection).await {
+            Ok(outcome) => {
+                tracing::info!(
+                    adopted = outcome.adopted.len(),
+                    incomplete = outcome.incomplete.len(),
+                    "Adopted torrents"
+                );
+                self.bot.notify_adopted(&outcome).await;
+            }
+            Err(e) => tracing::error!(error = ?e, "Failed to adopt torrents"),
+        }
+    }
+
+    /// Sync `[[rss]]` rules and rules added through the bot to the seedboxes.
+    ///
+    /// Rules removed from the configuration since the previous start are
+    /// removed from their seedbox as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if querying or updating the storage fails. Failures of
+    /// individual rules are logged and reported to the chat.
+    pub async fn sync_rss_rules(&self) -> anyhow::Result<()> {
+        for managed in self.store.list_rss_rules().await? {
+            let rule = &managed.rule;
+            if !managed.from_config || self.config.rss.iter().any(|r| r.name == rule.name) {
+                continue;
+            }
+
+            tracing::info!(rule = %rule.name, "Removing RSS rule dropped from configuration");
+            let (seedbox, _) = self.config.rss_rule_target(rule);
+            if let Err(e) = self.seedbox(seedbox)?.remove_rss_rule(&rule.name).await {
+                tracing::warn!(error = ?e, rule = %rule.name, "Failed to remove RSS rule");
+            }
+            self.store.delete_rss_rule(&rule.name).await?;
+        }
+
+        for rule in &self.config.rss {
+            self.store.save_rss_rule(rule, true).await?;
+        }
+
+        let rules = self.store.list_rss_rules().await?;
+        for managed in &rules {
+            if let Err(e) = self.push_rss_rule(&managed.rule).await {
+                tracing::warn!(error = ?e, rule = %managed.rule.name, "Failed to sync RSS rule");
+                self.bot
+                    .notify_rss_rule(&managed.rule.name, Err(e.to_string()))
+                    .await;
+            }
+        }
+        tracing::info!(count = rules.len(), "Synced RSS rules");
+
+        Ok(())
+    }
+
+    /// Create or replace an RSS rule on its seedbox.
+    async fn push_rss_rule(&self, rule: &RssRule) -> anyhow::Result<()> {
+        let (seedbox, tag) = self.config.rss_rule_target(rule);
+        self.seedbox(seedbox)?.set_rss_rule(rule, tag).await
+    }
+
+    /// Handle `/rss` bot commands, reporting the result to the chat.
+    #[instrument(skip(self))]
+    pub async fn run_rss_command(&self, cmd: RssCommand) {
+        let result: anyhow::Result<()> = async {
+            match cmd {
+                RssCommand::List => {
+                    let rules = self.store.list_rss_rules().await?;
+                    self.bot.report_rss_rules(&rules).await?;
+                }
+                RssCommand::Add(rule) => {
+                    let result = self.add_rss_rule(&rule).await;
+                    self.bot
+                        .notify_rss_rule(&rule.name, result.map(|()| "added"))
+                        .await;
+                }
+                RssCommand::Remove(name) => {
+                    let result = self.remove_rss_rule(&name).await;
+                    self.bot
+                        .notify_rss_rule(&name, result.map(|()| "removed"))
+                        .await;
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!(error = ?e, "Failed to handle RSS command");
+        }
+    }
+
+    /// Add an RSS rule through the bot, returning a user-facing error.
+    async fn add_rss_rule(&self, rule: &RssRule) -> Result<(), String> {
+        self.config.validate_rss_rule(rule)?;
+        let existing = self
+            .store
+            .list_rss_rules()
+            .await
+            .map_err(|e| e.to_string())?;
+        if existing
+            .iter()
+            .any(|managed| managed.from_config && managed.rule.name == rule.name)
+        {
+            return Err("rule is defined in the configuration".to_string());
+        }
+
+        self.push_rss_rule(rule).await.map_err(|e| e.to_string())?;
+        self.store
+            .save_rss_rule(rule, false)
+            .await
+            .map_err(|e| e.to_string())?;
+        tracing::info!(rule = %rule.name, "Added RSS rule");
+
+        Ok(())
+    }
+
+    /// Remove an RSS rule added through the bot, returning a user-facing error.
+    async fn remove_rss_rule(&self, name: &str) -> Result<(), String> {
+        let rules = self
+            .store
+            .list_rss_rules()
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(managed) = rules.iter().find(|managed| managed.rule.name == name) else {
+            return Err("rule is not managed by anipler".to_string());
+        };
+        if managed.from_config {
+            return Err("rule is defined in the configuration".to_string());
+        }
+
+        let (seedbox, _) = self.config.rss_rule_target(&managed.rule);
+        self.seedbox(seedbox)
+            .map_err(|e| e.to_string())?
+            .remove_rss_rule(name)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.store
+            .delete_rss_rule(name)
+            .await
+            .map_err(|e| e.to_string())?;
+        tracing::info!(rule = %name, "Removed RSS rule");
+
+        Ok(())
+    }
+
     /// Build and send `/report` message to Telegram chat.
     #[instrument(skip(self))]
     pub async fn run_report_job(&self) {
         let _result: anyhow::Result<()> = async {
+            let tracked = self.store.list_tracked_torrents().await?;
             let torrents = self.store.list_ready_torrents().await?;
             let artifacts = self.store.list_ready_artifacts().await?;
+            let missing = self.store.list_missing_tasks().await?;
+            let free_space = self.store.list_free_space().await?;
+            let failed = self.store.list_failed_tasks().await?;
             let mut report_torrents = Vec::with_capacity(torrents.len());
             for torrent in &torrents {
                 report_torrents.push(ReportTorrentInfo {
                     hash: torrent.hash.clone(),
                     name: torrent.name.clone(),
                     transfer_state: self.transmitter.transfer_state(&torrent.hash).await,
+                    transfer_progress: self.transmitter.transfer_progress(&torrent.hash).await,
                 });
             }
             self.bot
-                .report_available(&report_torrents, &artifacts)
+                .report_available(
+                    &tracked,
+                    &report_torrents,
+                    &artifacts,
+                    &missing,
+                    &failed,
+                    &free_space,
+                )
                 .await?;
             Ok(())
         }
@@ -356,31 +790,307 @@ impl AniplerDaemon {
         });
     }
 
-    /// Fetch the latest torrent status from the seedbox and update the local storage.
+    /// Client of the seedbox a task belongs to.
+    fn seedbox(&self, name: &str) -> Result<&dyn Seedbox, AniplerDaemonError> {
+        self.seedboxes
+            .get(name)
+            .map(AsRef::as_ref)
+            .ok_or_else(|| AniplerDaemonError::UnknownSeedbox(name.to_string()))
+    }
+
+    /// Fetch the latest torrent status from one seedbox and update the local storage.
     ///
     /// # Errors
     ///
     /// Returns an error if querying the seedbox or updating the storage fails.
-    pub async fn update_status(&self) -> anyhow::Result<()> {
-        tracing::debug!("Querying seedbox for torrent updates");
+    pub async fn update_status(&self, name: &str, seedbox: &dyn Seedbox) -> anyhow::Result<()> {
+        tracing::debug!(seedbox = %name, "Querying seedbox for torrent updates");
 
         let earliest_import_date = self.store.earliest_import_date().await?;
         tracing::trace!(earliest_date = %earliest_import_date, "Earliest import date for query");
 
-        let torrents = self.seedbox.query_torrents(earliest_import_date).await?;
+        let mut torrents = seedbox.query_torrents(earliest_import_date).await?;
         tracing::debug!(
+            seedbox = %name,
             count = torrents.len(),
             "Received torrent updates from seedbox"
         );
 
-        self.store.update_torrent_info(&torrents).await?;
-        tracing::info!("Updated torrent information in storage");
+        for torrent in &mut torrents {
+            torrent.seedbox = name.to_string();
+        }
+        if let Err(e) = self.store_torrent_updates(name, &torrents).await {
+            // Incremental backends would not report these changes again.
+            seedbox.reset_sync().await;
+            return Err(e.into());
+        }
+
+        let present = seedbox.torrent_hashes().await?;
+        let missing = self.store.reconcile_missing(name, &present).await?;
+        if !missing.is_empty() {
+            tracing::warn!(seedbox = %name, count = missing.len(), "Torrents vanished from seedbox");
+            self.bot.notify_missing(&missing).await;
+        }
+
+        Ok(())
+    }
+
+    /// Store torrent updates from one seedbox and report content changed
+    /// after transfer.
+    async fn store_torrent_updates(
+        &self,
+        name: &str,
+        torrents: &[TorrentTaskInfo],
+    ) -> Result<(), StorageManagerError> {
+        self.store.update_torrent_info(torrents).await?;
+        tracing::info!(seedbox = %name, "Updated torrent information in storage");
+
+        let requeue = self.config.transfer.retransfer_on_change;
+        let changed = self.store.detect_content_changes(torrents, requeue).await?;
+        if !changed.is_empty() {
+            tracing::warn!(seedbox = %name, count = changed.len(), "Torrent content changed after transfer");
+            self.bot.notify_content_changed(&changed, requeue).await;
+        }
+
+        Ok(())
+    }
+
+    /// Alert about tracked torrents stalled or errored for longer than
+    /// `alerts.unhealthy_after`, once per episode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if querying or updating the storage fails.
+    pub async fn alert_unhealthy(&self) -> anyhow::Result<()> {
+        let threshold = chrono::Utc::now()
+            - chrono::Duration::minutes(
+                i64::try_from(self.config.alerts.unhealthy_after).unwrap_or(i64::MAX),
+            );
+
+        for task in self.store.list_unhealthy_tasks(threshold).await? {
+            tracing::warn!(torrent = %task.name, hash = %task.hash, status = %task.status, since = %task.since, "Torrent is unhealthy");
+            self.bot.notify_unhealthy(&task).await;
+            self.store.mark_unhealthy_alerted(&task.hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply the tag of the first matching `[[auto_tag]]` rule to untagged
+    /// torrents. They are tracked from the next poll on. Torrents already known
+    /// to the storage, unless missing, are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if querying the seedbox or the storage fails. Failures
+    /// of individual torrents are logged and retried on the next run.
+    pub async fn auto_tag(&self, name: &str, seedbox: &dyn Seedbox) -> anyhow::Result<()> {
+        if self.auto_tagger.is_empty() {
+            return Ok(());
+        }
+
+        let earliest_import_date = self.store.earliest_import_date().await?;
+        for torrent in seedbox.untracked_torrents(earliest_import_date).await? {
+            let Some((rule, tag)) = self.auto_tagger.find(&torrent) else {
+                continue;
+            };
+            // Archived torrents may have lost their tag on purpose, see `archive.remove_tag`.
+            if self
+                .store
+                .task_status_by_hash(&torrent.hash)
+                .await?
+                .is_some_and(|status| status != TaskStatus::Missing)
+            {
+                tracing::trace!(torrent = %torrent.name, hash = %torrent.hash, "Skipping auto tag of known torrent");
+                continue;
+            }
+
+            tracing::info!(torrent = %torrent.name, hash = %torrent.hash, seedbox = %name, rule = rule, tag = %tag, "Auto-tagging rule matched");
+            if let Err(e) = seedbox.add_tag(&torrent.hash, tag).await {
+                tracing::warn!(error = ?e, torrent = %torrent.name, hash = %torrent.hash, "Failed to apply auto tag");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record free space of a seedbox, alert once it drops below
+    /// `alerts.free_space_below`, and pause or resume tracked downloads if
+    /// `alerts.pause_on_low_space` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if querying the seedbox or the storage fails.
+    pub async fn check_free_space(&self, name: &str, seedbox: &dyn Seedbox) -> anyhow::Result<()> {
+        let Some(free_space) = seedbox.free_space().await? else {
+            return Ok(());
+        };
+        let alerts = &self.config.alerts;
+        let low_space = alerts
+            .free_space_threshold()
+            .is_some_and(|threshold| free_space < threshold);
+        let was_low = self
+            .store
+            .record_free_space(name, free_space, low_space)
+            .await?;
+        tracing::debug!(seedbox = %name, free_space = free_space, low_space = low_space, "Recorded seedbox free space");
+
+        if !alerts.pause_on_low_space {
+            if low_space && !was_low {
+                tracing::warn!(seedbox = %name, free_space = free_space, "Seedbox is low on space");
+                self.bot.notify_low_space(name, free_space, 0).await;
+            }
+            return Ok(());
+        }
+
+        // Downloads added while space is low are paused on the next poll.
+        let hashes = self.store.list_tracked_hashes(name, !low_space).await?;
+        if low_space {
+            if !hashes.is_empty() {
+                seedbox.pause_torrents(&hashes).await?;
+                self.store.set_paused(&hashes, true).await?;
+                tracing::info!(seedbox = %name, count = hashes.len(), "Paused downloads for low space");
+            }
+            if !was_low {
+                tracing::warn!(seedbox = %name, free_space = free_space, "Seedbox is low on space");
+                self.bot
+                    .notify_low_space(name, free_space, hashes.len())
+                    .await;
+            }
+        } else if !hashes.is_empty() {
+            seedbox.resume_torrents(&hashes).await?;
+            self.store.set_paused(&hashes, false).await?;
+            tracing::info!(seedbox = %name, count = hashes.len(), "Resumed downloads paused for low space");
+            self.bot
+                .notify_downloads_resumed(name, free_space, hashes.len())
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Run the configured post-archive actions on archived torrents.
+    ///
+    /// Torrents waiting for their share limits before deletion stay pending
+    /// and are checked again on the next run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if querying or updating the storage fails. Failures of
+    /// individual torrents are logged and retried on the next run.
+    pub async fn cleanup_archived(&self) -> anyhow::Result<()> {
+        if !self.config.archive.is_enabled() {
+            return Ok(());
+        }
+
+        let tasks = self.store.list_pending_cleanups().await?;
+        tracing::debug!(
+            count = tasks.len(),
+            "Found archived torrents pending cleanup"
+        );
+
+        for cleanup in &tasks {
+            let task = &cleanup.task;
+            match self.cleanup_archived_task(cleanup).await {
+                Ok(true) => self.store.finish_cleanup(&task.hash).await?,
+                Ok(false) => {
+                    tracing::trace!(torrent = %task.name, hash = %task.hash, "Waiting for share limits before deletion");
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, torrent = %task.name, hash = %task.hash, "Failed to clean up archived torrent");
+                }
+            }
+        }
 
         Ok(())
     }
 
+    /// Apply post-archive actions to one torrent.
+    ///
+    /// Tags and share limits are applied once, later calls only check whether
+    /// the torrent can be deleted. Returns `Ok(false)` if deletion is still
+    /// waiting for the share limits.
+    async fn cleanup_archived_task(&self, cleanup: &PendingCleanup) -> anyhow::Result<bool> {
+        let task = &cleanup.task;
+        let archive = &self.config.archive;
+        let seedbox = self.seedbox(&task.seedbox)?;
+
+        let Some(stats) = seedbox.share_stats(&task.hash).await? else {
+            tracing::info!(torrent = %task.name, hash = %task.hash, "Archived torrent is gone from seedbox");
+            return Ok(true);
+        };
+
+        let seeding_time_limit = archive
+            .seeding_time_limit
+            .map(|minutes| Duration::from_secs(minutes * 60));
+        if !cleanup.actions_applied {
+            if archive.remove_tag {
+                seedbox.remove_tag(&task.hash, &task.tag).await?;
+            }
+            if let Some(tag) = &archive.add_tag {
+                seedbox.add_tag(&task.hash, tag).await?;
+            }
+            if archive.ratio_limit.is_some() || seeding_time_limit.is_some() {
+                seedbox
+                    .set_share_limits(&task.hash, archive.ratio_limit, seeding_time_limit)
+                    .await?;
+            }
+            self.store.mark_cleanup_applied(&task.hash).await?;
+        }
+
+        let delete_data = match archive.delete {
+            DeleteMode::Keep => {
+                tracing::info!(torrent = %task.name, hash = %task.hash, "Cleaned up archived torrent");
+                return Ok(true);
+            }
+            DeleteMode::Torrent => false,
+            DeleteMode::WithData => true,
+        };
+
+        let limit_reached = match (archive.ratio_limit, seeding_time_limit) {
+            (None, None) => true,
+            (ratio, time) => {
+                ratio.is_some_and(|ratio| stats.ratio >= ratio)
+                    || time.is_some_and(|time| stats.seeding_time >= time)
+            }
+        };
+        if !limit_reached {
+            return Ok(false);
+        }
+
+        seedbox.delete_torrent(&task.hash, delete_data).await?;
+        tracing::info!(torrent = %task.name, hash = %task.hash, delete_data = delete_data, "Deleted archived torrent from seedbox");
+
+        Ok(true)
+    }
+
+    /// Add a torrent to the seedbox and start tracking it immediately.
+    ///
+    /// The torrent is tagged with `tag`, or the default tag if not given, and
+    /// added to the named seedbox, or the first configured one if not given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tag is not tracked, the seedbox is unknown, the
+    /// seedbox rejects the torrent or updating the storage fails.
+    pub async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: Option<&str>,
+        seedbox: Option<&str>,
+    ) -> Result<TorrentTaskInfo, UploadError> {
+        upload::upload_torrent(
+            &self.store,
+            &self.seedboxes,
+            &self.config.tag_names(),
+            source,
+            tag,
+            seedbox,
+        )
+        .await
+    }
+
     #[instrument(skip(self))]
-    #[allow(clippy::needless_pass_by_value)] // We might carry payload in the future.
     pub fn handle_command(self: Arc<Self>, cmd: BotCommand) {
         match cmd {
             BotCommand::PullJob => {
@@ -395,6 +1105,51 @@ impl AniplerDaemon {
                     self.run_transfer_job().await;
                 });
             }
+            BotCommand::PriorityTransfer(prefixes) => {
+                tracing::info!(
+                    command = "transfer",
+                    "User requested priority transfer via bot"
+                );
+                tokio::spawn(async move {
+                    self.run_priority_transfer(&prefixes).await;
+                });
+            }
+            BotCommand::CancelTransfer(prefix) => {
+                tracing::info!(
+                    command = "cancel",
+                    "User requested transfer cancellation via bot"
+                );
+                tokio::spawn(async move {
+                    self.run_cancel_transfer(&prefix).await;
+                });
+            }
+            BotCommand::Retry(prefix) => {
+                tracing::info!(
+                    command = "retry",
+                    "User requested retry of failed transfers via bot"
+                );
+                tokio::spawn(async move {
+                    self.run_retry_job(prefix.as_deref()).await;
+                });
+            }
+            BotCommand::Adopt(selection) => {
+                tracing::info!(
+                    command = "adopt",
+                    "User requested adoption of torrents via bot"
+                );
+                tokio::spawn(async move {
+                    self.run_adopt_job(&selection).await;
+                });
+            }
+            BotCommand::Rss(cmd) => {
+                tracing::info!(
+                    command = "rss",
+                    "User requested RSS rule management via bot"
+                );
+                tokio::spawn(async move {
+                    self.run_rss_command(cmd).await;
+                });
+            }
             BotCommand::ReportAvailable => {
                 tracing::info!(
                     command = "report",
@@ -416,4 +1171,102 @@ pub enum AniplerDaemonError {
     RsyncTransfer(#[from] RsyncTransmitterError),
     #[error("Storage manager error: {0}")]
     Storage(#[from] StorageManagerError),
+    #[error("Seedbox error: {0}")]
+    Seedbox(anyhow::Error),
+    #[error("No file of the torrent matches the transfer rules")]
+    EmptyManifest,
+    #[error("Seedbox {0} is not configured")]
+    UnknownSeedbox(String),
+}
+
+impl AniplerDaemonError {
+    /// Whether retrying the failed operation later may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RsyncTransfer(e) => e.is_transient(),
+            Self::InvalidQBitApiResponse(_) | Self::Storage(_) | Self::Seedbox(_) => true,
+            Self::EmptyManifest | Self::UnknownSeedbox(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::task::{TorrentStats, TorrentStatus};
+
+    async fn dry_run_daemon(dir: &Path) -> Arc<AniplerDaemon> {
+        let ssh_key = dir.join("id_ed25519");
+        std::fs::write(&ssh_key, "test-key").unwrap();
+        let toml = format!(
+            r#"
+storage_path = "{}"
+stateless = true
+
+[qbit]
+url = "http://localhost:8081"
+username = "admin"
+password = "password"
+
+[seedbox]
+ssh_host = "seedbox.example"
+ssh_key = "{}"
+
+[transfer]
+dry_run = true
+
+[telegram]
+bot_token = "bot-token"
+chat_id = 42
+
+[api]
+key = "api-key"
+"#,
+            dir.to_string_lossy(),
+            ssh_key.to_string_lossy()
+        );
+        let config = DaemonConfig::load_from_toml(&toml, None, Default::default()).unwrap();
+
+        AniplerDaemon::from_config(config).await.unwrap()
+    }
+
+    fn seeding_torrent(hash: &str) -> TorrentTaskInfo {
+        TorrentTaskInfo {
+            hash: hash.to_string(),
+            status: TorrentStatus::Seeding,
+            content_path: format!("/downloads/{hash}"),
+            name: format!("Torrent {hash}"),
+            tag: "anipler".to_string(),
+            category: None,
+            save_path: Some("/downloads".to_string()),
+            stats: TorrentStats::default(),
+            seedbox: "default".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_transfers_are_held_back_from_transfer_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let daemon = dry_run_daemon(dir.path()).await;
+        daemon
+            .store
+            .update_torrent_info(&[seeding_torrent("aaaa"), seeding_torrent("bbbb")])
+            .await
+            .unwrap();
+        daemon.store.hold_transfer("aaaa").await.unwrap();
+
+        daemon.run_transfer_job().await;
+
+        // Dry runs only prepare the artifact directory of transferred torrents.
+        let transferred = |hash| {
+            daemon
+                .store
+                .artifact_storage_path(hash, "anipler", None)
+                .exists()
+        };
+        assert!(!transferred("aaaa"));
+        assert!(transferred("bbbb"));
+    }
 }
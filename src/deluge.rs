@@ -0,0 +1,473 @@
+//! Deluge Web UI JSON-RPC seedbox backend.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use async_trait::async_trait;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use qbit_rs::model::TorrentSource;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use url::Url;
+
+use crate::{
+    config::DelugeConfig,
+    seedbox::{Seedbox, matching_tag},
+    task::{TorrentStats, TorrentStatus, TorrentTaskInfo},
+};
+
+const TORRENT_KEYS: &[&str] = &[
+    "hash",
+    "name",
+    "progress",
+    "save_path",
+    "time_added",
+    "label",
+];
+
+/// Error code reported by Deluge Web when the session cookie is missing or expired.
+const NOT_AUTHENTICATED_CODE: i64 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DelugeSeedboxError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Deluge RPC {method} failed: {message}")]
+    Rpc { method: String, message: String },
+    #[error("Deluge Web login failed")]
+    LoginFailed,
+    #[error("Deluge Web has no daemon to connect to")]
+    NoDaemon,
+    #[error("Deluge RPC responded with an invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Seedbox backend talking to Deluge through the Web UI JSON-RPC.
+///
+/// Deluge has no tags, anipler tracks torrents whose label (of the Label
+/// plugin) is a tracked tag instead. Deluge labels are always lowercase.
+pub struct DelugeSeedbox {
+    /// HTTP client keeping the `_session_id` cookie of the Web UI.
+    client: reqwest::Client,
+    url: Url,
+    password: String,
+    request_id: AtomicU64,
+    /// Tracked tags in order of precedence.
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    message: String,
+    code: i64,
+}
+
+#[derive(Deserialize)]
+struct DelugeTorrent {
+    hash: String,
+    name: String,
+    /// Download progress in percent.
+    progress: f64,
+    save_path: String,
+    /// Unix timestamp, reported as float by Deluge.
+    time_added: f64,
+    #[serde(default)]
+    label: String,
+}
+
+impl DelugeSeedbox {
+    /// Create a Deluge Web client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client can not be built.
+    pub fn from_config(
+        config: &DelugeConfig,
+        tags: Vec<String>,
+    ) -> Result<Self, DelugeSeedboxError> {
+        tracing::debug!(endpoint = %config.url, "Creating Deluge seedbox client");
+
+        let client = reqwest::Client::builder().cookie_store(true).build()?;
+        let url = config.url.join("json").map_err(|e| {
+            DelugeSeedboxError::InvalidResponse(format!("Invalid Deluge Web URL: {e}"))
+        })?;
+
+        Ok(Self {
+            client,
+            url,
+            password: config.password.clone(),
+            request_id: AtomicU64::new(0),
+            tags,
+        })
+    }
+
+    /// Call a JSON-RPC method, logging in again once if the session expired.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T, DelugeSeedboxError> {
+        match self.call_once(method, &params).await {
+            Err(DelugeSeedboxError::Rpc { .. }) if !self.is_authenticated().await? => {
+                self.login().await?;
+                self.call_once(method, &params).await
+            }
+            res => res,
+        }
+    }
+
+    async fn call_once<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<T, DelugeSeedboxError> {
+        let response = self.raw_call(method, params).await?;
+
+        if let Some(error) = response.error {
+            return Err(DelugeSeedboxError::Rpc {
+                method: method.to_string(),
+                message: error.message,
+            });
+        }
+
+        let result = response.result.unwrap_or(serde_json::Value::Null);
+        serde_json::from_value(result).map_err(|e| {
+            DelugeSeedboxError::InvalidResponse(format!("Unexpected {method} result: {e}"))
+        })
+    }
+
+    async fn raw_call(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<RpcResponse<serde_json::Value>, DelugeSeedboxError> {
+        let id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({ "method": method, "params": params, "id": id });
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Whether the current session cookie is still valid.
+    async fn is_authenticated(&self) -> Result<bool, DelugeSeedboxError> {
+        let response = self.raw_call("auth.check_session", &json!([])).await?;
+        Ok(match response.error {
+            Some(error) if error.code == NOT_AUTHENTICATED_CODE => false,
+            _ => response
+                .result
+                .and_then(|r| r.as_bool())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Log in to the Web UI and make sure it is connected to a Deluge daemon.
+    async fn login(&self) -> Result<(), DelugeSeedboxError> {
+        tracing::debug!("Logging in to Deluge Web");
+
+        let logged_in: bool = self
+            .call_once("auth.login", &json!([self.password]))
+            .await?;
+        if !logged_in {
+            return Err(DelugeSeedboxError::LoginFailed);
+        }
+
+        let connected: bool = self.call_once("web.connected", &json!([])).await?;
+        if connected {
+            return Ok(());
+        }
+
+        // Each host is `[id, host, port, status]`.
+        let hosts: Vec<Vec<serde_json::Value>> =
+            self.call_once("web.get_hosts", &json!([])).await?;
+        let host_id = hosts
+            .first()
+            .and_then(|host| host.first())
+            .and_then(|id| id.as_str())
+            .ok_or(DelugeSeedboxError::NoDaemon)?;
+        tracing::debug!(host = %host_id, "Connecting Deluge Web to daemon");
+        let _: serde_json::Value = self.call_once("web.connect", &json!([host_id])).await?;
+
+        Ok(())
+    }
+
+    /// Apply a label to a torrent, creating the label if needed.
+    async fn apply_label(&self, hash: &str, label: &str) -> Result<(), DelugeSeedboxError> {
+        // `label.add` fails if the label already exists.
+        if let Err(e) = self
+            .call::<serde_json::Value>("label.add", json!([label]))
+            .await
+        {
+            tracing::trace!(error = ?e, "Label might already exist");
+        }
+
+        self.call::<serde_json::Value>("label.set_torrent", json!([hash, label]))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_torrent(&self, hash: &str) -> Result<DelugeTorrent, DelugeSeedboxError> {
+        self.call("core.get_torrent_status", json!([hash, TORRENT_KEYS]))
+            .await
+    }
+}
+
+impl DelugeTorrent {
+    fn into_task_info(self, tag: String) -> TorrentTaskInfo {
+        let status = if self.progress < 100.0 {
+            TorrentStatus::Downloading
+        } else {
+            TorrentStatus::Seeding
+        };
+        let content_path = format!("{}/{}", self.save_path.trim_end_matches('/'), self.name);
+
+        TorrentTaskInfo {
+            hash: self.hash,
+            status,
+            content_path,
+            name: self.name,
+            tag,
+            // The Label plugin is used for tags, Deluge has no categories.
+            category: None,
+            save_path: Some(self.save_path),
+            stats: TorrentStats {
+                progress: self.progress / 100.0,
+                ..TorrentStats::default()
+            },
+            seedbox: String::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Seedbox for DelugeSeedbox {
+    async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: &str,
+    ) -> anyhow::Result<TorrentTaskInfo> {
+        let requests = match source {
+            // `Sep` renders as newline separated list.
+            TorrentSource::Urls { urls } => urls
+                .to_string()
+                .lines()
+                .map(|url| {
+                    if url.starts_with("magnet:") {
+                        ("core.add_torrent_magnet", json!([url, {}]))
+                    } else {
+                        ("core.add_torrent_url", json!([url, {}]))
+                    }
+                })
+                .collect::<Vec<_>>(),
+            TorrentSource::TorrentFiles { torrents } => torrents
+                .iter()
+                .map(|t| {
+                    let data = BASE64_STANDARD.encode(&t.data);
+                    ("core.add_torrent_file", json!([t.filename, data, {}]))
+                })
+                .collect(),
+        };
+
+        let mut uploaded = None;
+        for (method, params) in requests {
+            let hash: Option<String> = self.call(method, params).await?;
+            let hash = hash.ok_or_else(|| {
+                DelugeSeedboxError::InvalidResponse(format!("{method} returned no torrent hash"))
+            })?;
+
+            self.apply_label(&hash, tag).await?;
+            let info = self
+                .get_torrent(&hash)
+                .await?
+                .into_task_info(tag.to_string());
+            tracing::info!(torrent = %info.name, hash = %info.hash, "Uploaded torrent");
+            uploaded.get_or_insert(info);
+        }
+
+        uploaded.ok_or_else(|| anyhow::anyhow!("No torrent given for upload"))
+    }
+
+    async fn query_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<TorrentTaskInfo>> {
+        tracing::debug!("Querying Deluge Web for torrents");
+
+        let torrents: HashMap<String, DelugeTorrent> = self
+            .call(
+                "core.get_torrents_status",
+                json!([{ "label": self.tags }, TORRENT_KEYS]),
+            )
+            .await?;
+
+        let mut ignored_count = 0;
+        let mut tracked_count = 0;
+
+        #[allow(clippy::cast_precision_loss)]
+        let earliest_import_date = earliest_import_date.timestamp() as f64;
+        let torrents = torrents
+            .into_values()
+            .filter_map(|t| matching_tag(&self.tags, &[t.label.as_str()]).map(|tag| (t, tag)))
+            .filter_map(|(t, tag)| {
+                if t.time_added < earliest_import_date {
+                    ignored_count += 1;
+                    tracing::trace!(torrent = %t.name, hash = %t.hash, "Ignoring torrent");
+                    return None;
+                }
+
+                tracked_count += 1;
+                tracing::trace!(torrent = %t.name, hash = %t.hash, "Tracking torrent");
+
+                Some(t.into_task_info(tag))
+            })
+            .collect::<Vec<_>>();
+
+        tracing::debug!(
+            tracked = tracked_count,
+            ignored = ignored_count,
+            "Queried torrents from RPC"
+        );
+
+        Ok(torrents)
+    }
+
+    async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>> {
+        let torrents: HashMap<String, DelugeTorrent> = self
+            .call(
+                "core.get_torrents_status",
+                json!([{ "label": self.tags }, TORRENT_KEYS]),
+            )
+            .await?;
+
+        let torrents = torrents
+            .into_values()
+            .filter_map(|t| matching_tag(&self.tags, &[t.label.as_str()]).map(|tag| (t, tag)))
+            .map(|(t, tag)| {
+                #[allow(clippy::cast_possible_truncation)]
+                let added = DateTime::from_timestamp(t.time_added as i64, 0).unwrap_or_default();
+                (t.into_task_info(tag), added)
+            })
+            .collect();
+
+        Ok(torrents)
+    }
+
+    async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>> {
+        let hashes: Vec<String> = self.call("core.get_session_state", json!([])).await?;
+        Ok(hashes.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, http::HeaderMap, routing::post};
+
+    use super::*;
+
+    const MOCK_COOKIE: &str = "_session_id=mock";
+
+    async fn mock_rpc(
+        headers: HeaderMap,
+        Json(body): Json<serde_json::Value>,
+    ) -> (HeaderMap, Json<serde_json::Value>) {
+        let authenticated = headers
+            .get("cookie")
+            .is_some_and(|v| v.as_bytes() == MOCK_COOKIE.as_bytes());
+        let id = body["id"].clone();
+        let mut response_headers = HeaderMap::new();
+
+        let response = match (body["method"].as_str(), authenticated) {
+            (Some("auth.login"), _) => {
+                response_headers.insert("set-cookie", MOCK_COOKIE.parse().unwrap());
+                json!({ "result": body["params"][0] == "secret", "error": null, "id": id })
+            }
+            (Some("auth.check_session"), authenticated) => {
+                json!({ "result": authenticated, "error": null, "id": id })
+            }
+            (_, false) => json!({
+                "result": null,
+                "error": { "message": "Not authenticated", "code": NOT_AUTHENTICATED_CODE },
+                "id": id
+            }),
+            (Some("web.connected"), true) => json!({ "result": true, "error": null, "id": id }),
+            (Some("core.get_torrents_status"), true) => {
+                assert_eq!(body["params"][0]["label"], json!(["anipler"]));
+                json!({
+                    "result": {
+                        "aaaa": {
+                            "hash": "aaaa",
+                            "name": "Show - 01",
+                            "progress": 42.0,
+                            "save_path": "/downloads/",
+                            "time_added": 2_000_000_000.0,
+                            "label": "anipler"
+                        },
+                        "bbbb": {
+                            "hash": "bbbb",
+                            "name": "Old",
+                            "progress": 100.0,
+                            "save_path": "/downloads",
+                            "time_added": 0.0,
+                            "label": "anipler"
+                        }
+                    },
+                    "error": null,
+                    "id": id
+                })
+            }
+            _ => json!({
+                "result": null,
+                "error": { "message": "Unknown method", "code": 2 },
+                "id": id
+            }),
+        };
+
+        (response_headers, Json(response))
+    }
+
+    #[tokio::test]
+    async fn query_torrents_logs_in_and_filters_by_label() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/json", post(mock_rpc));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let seedbox = DelugeSeedbox::from_config(
+            &DelugeConfig {
+                url: Url::parse(&format!("http://{addr}/")).unwrap(),
+                password: "secret".to_string(),
+            },
+            vec!["anipler".to_string()],
+        )
+        .unwrap();
+
+        let torrents = seedbox
+            .query_torrents(DateTime::from_timestamp(1_000_000_000, 0).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(torrents.len(), 1);
+        assert_eq!(torrents[0].hash, "aaaa");
+        assert!(matches!(torrents[0].status, TorrentStatus::Downloading));
+        assert_eq!(torrents[0].content_path, "/downloads/Show - 01");
+        assert_eq!(torrents[0].tag, "anipler");
+    }
+}
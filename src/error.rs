@@ -1,3 +1,8 @@
 pub use crate::daemon::AniplerDaemonError;
+pub use crate::deluge::DelugeSeedboxError;
 pub use crate::rsync::RsyncTransmitterError;
+pub use crate::rtorrent::RTorrentSeedboxError;
+pub use crate::sftp::SftpTransferError;
 pub use crate::storage::StorageManagerError;
+pub use crate::transmission::TransmissionSeedboxError;
+pub use crate::upload::UploadError;
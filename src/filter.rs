@@ -0,0 +1,119 @@
+//! Selection of torrent files to transfer.
+
+use globset::{GlobSet, GlobSetBuilder};
+
+use crate::config::TransferConfig;
+
+/// Include/exclude glob rules from `transfer.include` and `transfer.exclude`.
+///
+/// Patterns are matched against file paths as reported by the seedbox, i.e.
+/// relative to the directory containing the torrent content, so the root
+/// folder of a batch torrent is part of the path. `*` does not cross `/`.
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl FileFilter {
+    /// Compile the glob rules of the transfer configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any pattern is not a valid glob.
+    pub fn from_config(config: &TransferConfig) -> Result<Self, globset::Error> {
+        Ok(Self {
+            include: build_glob_set(&config.include)?,
+            exclude: build_glob_set(&config.exclude)?,
+        })
+    }
+
+    /// Whether any rule is configured. Without rules, torrents are transferred
+    /// as a whole and no file list is needed.
+    pub const fn is_active(&self) -> bool {
+        self.include.is_some() || self.exclude.is_some()
+    }
+
+    /// Whether a single file should be transferred.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+            && !self.exclude.as_ref().is_some_and(|set| set.is_match(path))
+    }
+
+    /// Select files to transfer, preserving their order.
+    pub fn select(&self, files: Vec<String>) -> Vec<String> {
+        files.into_iter().filter(|f| self.is_match(f)).collect()
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, globset::Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            globset::GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()?,
+        );
+    }
+
+    builder.build().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> FileFilter {
+        let config = TransferConfig {
+            include: include.iter().map(ToString::to_string).collect(),
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+            ..TransferConfig::default()
+        };
+        FileFilter::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn inactive_filter_selects_everything() {
+        let filter = filter(&[], &[]);
+
+        assert!(!filter.is_active());
+        assert!(filter.is_match("Show/Scans/01.jpg"));
+    }
+
+    #[test]
+    fn exclude_rules_drop_extras() {
+        let filter = filter(&[], &["**/Scans/**", "**/*NCOP*", "**/*.nfo"]);
+        let files = vec![
+            "Show/Show - 01.mkv".to_string(),
+            "Show/Scans/01.jpg".to_string(),
+            "Show/Extras/Show - NCOP.mkv".to_string(),
+            "Show/Show.nfo".to_string(),
+            "Show.nfo".to_string(),
+        ];
+
+        assert_eq!(filter.select(files), vec!["Show/Show - 01.mkv"]);
+    }
+
+    #[test]
+    fn include_rules_are_applied_before_excludes() {
+        let filter = filter(&["**/*.mkv", "**/*.ass"], &["**/*NCED*"]);
+
+        assert!(filter.is_match("Show/Show - 01.mkv"));
+        assert!(filter.is_match("Show/Subs/Show - 01.ass"));
+        assert!(!filter.is_match("Show/Show - NCED.mkv"));
+        assert!(!filter.is_match("Show/Show - 01.mka"));
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        let config = TransferConfig {
+            exclude: vec!["[".to_string()],
+            ..TransferConfig::default()
+        };
+
+        assert!(FileFilter::from_config(&config).is_err());
+    }
+}
@@ -1,13 +1,23 @@
+mod adopt;
 mod api;
+mod autotag;
 pub mod bot;
 pub mod config;
 pub mod daemon;
+mod deluge;
 pub mod error;
+mod filter;
 pub mod model;
 pub mod puller;
 mod qbit;
 mod rsync;
+mod rtorrent;
+mod seedbox;
+mod sftp;
 mod storage;
 mod task;
+mod transmission;
+mod upload;
+mod xmlrpc;
 
 pub use api::ApiServer;
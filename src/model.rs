@@ -1 +1,2 @@
+pub use crate::task::{TorrentStatus, TorrentTaskInfo};
 pub use qbit_rs::model::TorrentSource;
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use shell_words;
@@ -11,6 +14,8 @@ pub struct AniplerPuller {
     artifacts: Mutex<Vec<ArtifactInfo>>,
     auth_header: String,
     base_url: Url,
+    /// Destinations by seedbox category, overriding `destination`.
+    category_destinations: HashMap<String, PathBuf>,
     destination: PathBuf,
     reqwest: reqwest::Client,
     ssh_host: String,
@@ -24,6 +29,11 @@ impl AniplerPuller {
             base_url: config.api_url,
             reqwest: reqwest::Client::new(),
             ssh_host: config.ssh_host,
+            category_destinations: config
+                .categories
+                .into_iter()
+                .map(|category| (category.name, category.destination))
+                .collect(),
             destination: config.destination,
             artifacts: Mutex::new(Vec::new()),
         }
@@ -89,6 +99,15 @@ impl AniplerPuller {
         }
     }
 
+    /// Local destination directory of an artifact, chosen by its category.
+    fn destination_of(&self, artifact: &ArtifactInfo) -> &Path {
+        artifact
+            .category
+            .as_ref()
+            .and_then(|category| self.category_destinations.get(category))
+            .unwrap_or(&self.destination)
+    }
+
     /// Transfer the next artifact in the list via rsync.
     ///
     /// Return value indicates whether an artifact was transferred `Some(())` or if there were no artifacts to transfer `None`.
@@ -107,6 +126,8 @@ impl AniplerPuller {
         tracing::info!(hash = %hash, name = %artifact.name, "Transferring artifact");
 
         let source = format!("{}:{}", self.ssh_host, artifact.path);
+        let destination = self.destination_of(artifact);
+        tracing::debug!(category = ?artifact.category, destination = %destination.display(), "Selected artifact destination");
 
         let ssh_cmd = shell_words::join([
             "ssh",
@@ -123,7 +144,7 @@ impl AniplerPuller {
             .arg("--rsh")
             .arg(ssh_cmd)
             .arg(source)
-            .arg(&self.destination);
+            .arg(destination);
 
         tracing::debug!(command = ?rsync_cmd, "Executing rsync command");
 
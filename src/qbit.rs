@@ -1,98 +1,232 @@
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use qbit_rs::model::{Credential, GetTorrentListArg};
+use qbit_rs::model::{
+    AddTorrentArg, Credential, GetTorrentListArg, Priority, RatioLimit, SeedingTimeLimit,
+    SetTorrentSharedLimitArg, SyncData, Torrent, TorrentSource,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use tokio::sync::{Mutex, OnceCell};
+use url::Url;
 
 use crate::{
-    config::DaemonConfig,
-    task::{TorrentStatus, TorrentTaskInfo},
+    config::{QBitConfig, RssRule},
+    error::AniplerDaemonError,
+    seedbox::{Seedbox, ShareStats, UntrackedTorrent, matching_tag},
+    task::{TorrentStats, TorrentStatus, TorrentTaskInfo},
 };
 
-const ANIPLER_TORRENT_TAG: &str = "anipler";
+/// qBittorrent adds torrents asynchronously, so the uploaded torrent may not be
+/// listed immediately after `torrents/add` returns.
+const UPLOAD_LOOKUP_ATTEMPTS: u32 = 10;
+const UPLOAD_LOOKUP_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Web API version of qBittorrent 5.0, which renamed `torrents/pause` and
+/// `torrents/resume` to `torrents/stop` and `torrents/start`, and added
+/// `torrentParams` to RSS rules.
+const QBIT_5_API_VERSION: (u32, u32) = (2, 11);
 
 pub struct QBitSeedbox {
     endpoint: qbit_rs::Qbit,
+    web: WebApiClient,
+    /// Tracked tags in order of precedence.
+    tags: Vec<String>,
+    upload_counter: AtomicU64,
+    sync: Mutex<SyncState>,
+    /// Whether the Web API predates qBittorrent 5, queried on first use.
+    legacy_api: OnceCell<bool>,
+}
+
+/// Incremental `sync/maindata` state, kept across polls.
+///
+/// The `rid` is only meaningful for the current Web API session, so it is not
+/// stored on disk. A fresh daemon starts with `rid = 0`, which makes
+/// qBittorrent send a full update.
+#[derive(Default)]
+struct SyncState {
+    rid: i64,
+    /// Merged view of all torrents on the seedbox, keyed by hash.
+    torrents: HashMap<String, Torrent>,
+    /// `free_space_on_disk` of the server state.
+    free_space: Option<u64>,
+}
+
+/// Plain Web API client for requests whose `qbit_rs` models lack fields we
+/// need, such as `torrentParams` of RSS rules.
+struct WebApiClient {
+    /// HTTP client keeping the `SID` cookie.
+    client: reqwest::Client,
+    /// Base URL ending with `api/v2/`.
+    url: Url,
+    username: String,
+    password: String,
 }
 
 impl QBitSeedbox {
-    pub fn from_config(config: &DaemonConfig) -> Self {
-        tracing::debug!(endpoint = %config.qbit.url, "Creating qBittorrent seedbox client");
+    /// Create a qBittorrent Web API client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client can not be built.
+    pub fn from_config(config: &QBitConfig, tags: Vec<String>) -> anyhow::Result<Self> {
+        tracing::debug!(endpoint = %config.url, "Creating qBittorrent seedbox client");
 
-        let credential =
-            Credential::new(config.qbit.username.clone(), config.qbit.password.clone());
-        let endpoint = qbit_rs::Qbit::new(config.qbit.url.clone(), credential);
+        let credential = Credential::new(config.username.clone(), config.password.clone());
+        let endpoint = qbit_rs::Qbit::new(config.url.clone(), credential);
+        let web = WebApiClient {
+            client: reqwest::Client::builder().cookie_store(true).build()?,
+            url: config.url.join("api/v2/")?,
+            username: config.username.clone(),
+            password: config.password.clone(),
+        };
 
-        Self { endpoint }
+        Ok(Self {
+            endpoint,
+            web,
+            tags,
+            upload_counter: AtomicU64::new(0),
+            sync: Mutex::new(SyncState::default()),
+            legacy_api: OnceCell::new(),
+        })
     }
 
-    // pub async fn upload_torrent(&self, _source: &TorrentSource) -> anyhow::Result<TorrentTaskInfo> {
-    //     unimplemented!()
-    // }
+    async fn is_legacy_api(&self) -> anyhow::Result<bool> {
+        let legacy = self
+            .legacy_api
+            .get_or_try_init(|| async {
+                let version = self.endpoint.get_webapi_version().await?;
+                let legacy = is_legacy_api_version(&version);
+                tracing::debug!(version = %version, legacy, "Detected qBittorrent Web API version");
+                anyhow::Ok(legacy)
+            })
+            .await?;
 
-    /// Query all torrents that should managed by the program from the seedbox.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the API request fails or the response is missing required fields in any torrent.
-    pub async fn query_torrents(
+        Ok(*legacy)
+    }
+
+    async fn lookup_uploaded_torrent(
+        &self,
+        marker: &str,
+        tag: &str,
+    ) -> anyhow::Result<(TorrentTaskInfo, i64)> {
+        for _ in 0..UPLOAD_LOOKUP_ATTEMPTS {
+            let args = GetTorrentListArg {
+                filter: None,
+                category: None,
+                tag: Some(marker.to_string()),
+                sort: None,
+                reverse: None,
+                limit: None,
+                offset: None,
+                hashes: None,
+            };
+
+            if let Some(t) = self
+                .endpoint
+                .get_torrent_list(args)
+                .await?
+                .into_iter()
+                .next()
+            {
+                return Ok(torrent_task_info(t, tag.to_string())?);
+            }
+
+            tokio::time::sleep(UPLOAD_LOOKUP_INTERVAL).await;
+        }
+
+        Err(AniplerDaemonError::InvalidQBitApiResponse(format!(
+            "Uploaded torrent with tag {marker} not found"
+        ))
+        .into())
+    }
+}
+
+#[async_trait]
+impl Seedbox for QBitSeedbox {
+    /// `torrents/add` does not report the hash of the new torrent, so it is
+    /// added with an extra one-shot marker tag which is used to look it up and
+    /// deleted afterwards.
+    async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: &str,
+    ) -> anyhow::Result<TorrentTaskInfo> {
+        let marker = format!(
+            "anipler-upload-{}-{}",
+            Utc::now().timestamp(),
+            self.upload_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        tracing::debug!(marker = %marker, "Uploading torrent to qBittorrent");
+
+        let arg = AddTorrentArg {
+            source: source.clone(),
+            tags: Some(format!("{tag},{marker}")),
+            ..AddTorrentArg::default()
+        };
+        self.endpoint.add_torrent(arg).await?;
+
+        let lookup = self.lookup_uploaded_torrent(&marker, tag).await;
+
+        if let Err(e) = self.endpoint.delete_tags(vec![marker.clone()]).await {
+            tracing::warn!(error = ?e, marker = %marker, "Failed to delete upload marker tag");
+        }
+
+        let (info, _) = lookup?;
+        tracing::info!(torrent = %info.name, hash = %info.hash, "Uploaded torrent");
+
+        Ok(info)
+    }
+
+    /// Polls `sync/maindata` and only reports torrents changed since the
+    /// previous poll, or every torrent when qBittorrent sends a full update.
+    async fn query_torrents(
         &self,
         earliest_import_date: DateTime<Utc>,
     ) -> anyhow::Result<Vec<TorrentTaskInfo>> {
-        tracing::debug!("Querying qBittorrent API for torrents");
+        let mut sync = self.sync.lock().await;
+        tracing::debug!(
+            rid = sync.rid,
+            "Querying qBittorrent API for torrent updates"
+        );
 
-        let args = GetTorrentListArg {
-            filter: None,
-            category: None,
-            tag: Some(ANIPLER_TORRENT_TAG.to_string()),
-            sort: None,
-            reverse: None,
-            limit: None,
-            offset: None,
-            hashes: None,
+        let data = match self.endpoint.sync(sync.rid).await {
+            Ok(data) => data,
+            Err(e) => {
+                // We can't tell which deltas were lost, start over next time.
+                sync.reset();
+                return Err(e.into());
+            }
         };
 
+        let changed = sync.apply(data);
+        tracing::trace!(
+            rid = sync.rid,
+            changed = changed.len(),
+            "Applied qBittorrent sync data"
+        );
+
         let mut ignored_count = 0;
         let mut tracked_count = 0;
 
-        let torrents = self
-            .endpoint
-            .get_torrent_list(args)
-            .await?
-            .into_iter()
-            .filter_map(|t| {
-                macro_rules! extract_filed {
-                    ($opt:expr, $field:expr) => {{
-                        let Some(value) = $opt else {
-                            return Some(Err(
-                                crate::error::AniplerDaemonError::InvalidQBitApiResponse(format!(
-                                    "Missing field {} in torrent info",
-                                    $field
-                                )),
-                            ));
-                        };
-                        value
-                    }};
-                }
-
-                let hash = extract_filed!(t.hash, "hash");
-                let status = {
-                    let progress = extract_filed!(t.progress, "progress");
+        let torrents = changed
+            .iter()
+            .filter_map(|hash| sync.torrents.get(hash).map(|t| (hash, t)))
+            .filter_map(|(hash, t)| tracked_tag(&self.tags, t).map(|tag| (hash, t, tag)))
+            .filter_map(|(hash, t, tag)| {
+                let mut t = t.clone();
+                t.hash = Some(hash.clone());
 
-                    if progress < 1.0 {
-                        TorrentStatus::Downloading
-                    } else {
-                        TorrentStatus::Seeding
-                    }
-                };
-                let content_path = extract_filed!(t.content_path, "content_path");
-                let name = extract_filed!(t.name, "name");
-
-                let info = TorrentTaskInfo {
-                    hash,
-                    status,
-                    content_path,
-                    name,
+                let (info, added_on) = match torrent_task_info(t, tag) {
+                    Ok(res) => res,
+                    Err(e) => return Some(Err(e)),
                 };
 
-                let added_on = extract_filed!(t.added_on, "added_on");
                 if added_on < earliest_import_date.timestamp() {
                     ignored_count += 1;
                     tracing::trace!(torrent = %info.name, hash = %info.hash, "Ignoring torrent");
@@ -105,7 +239,12 @@ impl QBitSeedbox {
                 Some(Ok(info))
             })
             .map(|res| res.map_err(anyhow::Error::from))
-            .collect::<anyhow::Result<_>>()?;
+            .collect::<anyhow::Result<_>>();
+        if torrents.is_err() {
+            // The changes were merged already and would not be sent again.
+            sync.reset();
+        }
+        drop(sync);
 
         tracing::debug!(
             tracked = tracked_count,
@@ -113,6 +252,565 @@ impl QBitSeedbox {
             "Queried torrents from API"
         );
 
+        torrents
+    }
+
+    async fn reset_sync(&self) {
+        tracing::debug!("Resetting qBittorrent sync state");
+        self.sync.lock().await.reset();
+    }
+
+    /// Reads `torrents/files`, skipping files set to "do not download".
+    async fn torrent_files(&self, hash: &str) -> anyhow::Result<Option<Vec<String>>> {
+        let files = self
+            .endpoint
+            .get_torrent_contents(hash, None)
+            .await?
+            .into_iter()
+            .filter(|f| !matches!(f.priority, Priority::DoNotDownload))
+            .map(|f| f.name)
+            .collect();
+
+        Ok(Some(files))
+    }
+
+    async fn add_tag(&self, hash: &str, tag: &str) -> anyhow::Result<()> {
+        self.endpoint
+            .add_torrent_tags(vec![hash.to_string()], vec![tag.to_string()])
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_tag(&self, hash: &str, tag: &str) -> anyhow::Result<()> {
+        self.endpoint
+            .remove_torrent_tags(vec![hash.to_string()], Some(vec![tag.to_string()]))
+            .await?;
+        Ok(())
+    }
+
+    async fn set_share_limits(
+        &self,
+        hash: &str,
+        ratio: Option<f64>,
+        seeding_time: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        // qBittorrent requires all limits, fall back to the global ones.
+        let arg = SetTorrentSharedLimitArg {
+            hashes: vec![hash.to_string()].into(),
+            ratio_limit: Some(ratio.map_or(RatioLimit::Global, RatioLimit::Limited)),
+            seeding_time_limit: Some(seeding_time.map_or(SeedingTimeLimit::Global, |t| {
+                SeedingTimeLimit::Limited(t.as_secs() / 60)
+            })),
+            inactive_seeding_time_limit: Some(SeedingTimeLimit::Global),
+        };
+        self.endpoint.set_torrent_shared_limit(arg).await?;
+        Ok(())
+    }
+
+    async fn share_stats(&self, hash: &str) -> anyhow::Result<Option<ShareStats>> {
+        let args = GetTorrentListArg {
+            filter: None,
+            category: None,
+            tag: None,
+            sort: None,
+            reverse: None,
+            limit: None,
+            offset: None,
+            hashes: Some(hash.to_string()),
+        };
+
+        let Some(t) = self
+            .endpoint
+            .get_torrent_list(args)
+            .await?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let ratio = t.ratio.ok_or_else(|| {
+            AniplerDaemonError::InvalidQBitApiResponse("Missing field ratio in torrent info".into())
+        })?;
+        let seeding_time = t.seeding_time.ok_or_else(|| {
+            AniplerDaemonError::InvalidQBitApiResponse(
+                "Missing field seeding_time in torrent info".into(),
+            )
+        })?;
+
+        Ok(Some(ShareStats {
+            ratio,
+            seeding_time: Duration::from_secs(seeding_time.max(0).unsigned_abs()),
+        }))
+    }
+
+    async fn delete_torrent(&self, hash: &str, delete_data: bool) -> anyhow::Result<()> {
+        self.endpoint
+            .delete_torrents(vec![hash.to_string()], delete_data)
+            .await?;
+        Ok(())
+    }
+
+    /// Served from the `sync/maindata` cache of the previous poll.
+    async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>> {
+        let sync = self.sync.lock().await;
+        if sync.rid == 0 {
+            return Err(AniplerDaemonError::InvalidQBitApiResponse(
+                "Torrent list is not synchronized yet".to_string(),
+            )
+            .into());
+        }
+
+        let torrents = sync
+            .torrents
+            .iter()
+            .filter_map(|(hash, t)| tracked_tag(&self.tags, t).map(|tag| (hash, t, tag)))
+            .map(|(hash, t, tag)| {
+                let mut t = t.clone();
+                t.hash = Some(hash.clone());
+                let (info, added_on) = torrent_task_info(t, tag)?;
+                Ok((
+                    info,
+                    DateTime::from_timestamp(added_on, 0).unwrap_or_default(),
+                ))
+            })
+            .collect::<anyhow::Result<_>>();
+        drop(sync);
+
+        torrents
+    }
+
+    /// Served from the `sync/maindata` cache of the previous poll.
+    async fn untracked_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<UntrackedTorrent>> {
+        let sync = self.sync.lock().await;
+
+        let torrents = sync
+            .torrents
+            .iter()
+            .filter(|(_, t)| tracked_tag(&self.tags, t).is_none())
+            .filter(|(_, t)| {
+                t.added_on
+                    .is_some_and(|added_on| added_on >= earliest_import_date.timestamp())
+            })
+            .map(|(hash, t)| UntrackedTorrent {
+                hash: hash.clone(),
+                name: t.name.clone().unwrap_or_default(),
+                category: t.category.clone().filter(|c| !c.is_empty()),
+                tracker: t.tracker.clone().filter(|t| !t.is_empty()),
+                save_path: t.save_path.clone(),
+            })
+            .collect();
+        drop(sync);
+
         Ok(torrents)
     }
+
+    /// qBittorrent 5 takes the tags and category from `torrentParams`. Older
+    /// versions only support `assignedCategory` and ignore the tag, so rules
+    /// without a category to match with `[[auto_tag]]` are rejected there.
+    async fn set_rss_rule(&self, rule: &RssRule, tag: &str) -> anyhow::Result<()> {
+        if self.is_legacy_api().await? {
+            let Some(category) = &rule.category else {
+                anyhow::bail!(
+                    "qBittorrent 4.x ignores the tag of RSS rules, set a category and tag it with an [[auto_tag]] rule"
+                );
+            };
+            tracing::warn!(rule = %rule.name, category = %category, tag = %tag, "qBittorrent 4.x ignores the tag of RSS rules, relying on the category");
+        }
+
+        let mut params = json!({ "tags": [tag] });
+        let mut def = json!({
+            "enabled": true,
+            "mustContain": rule.must_contain,
+            "mustNotContain": rule.must_not_contain,
+            "useRegex": rule.use_regex,
+            "episodeFilter": rule.episode_filter,
+            "affectedFeeds": rule.feeds,
+        });
+        if let Some(category) = &rule.category {
+            params["category"] = json!(category);
+            def["assignedCategory"] = json!(category);
+        }
+        if let Some(save_path) = &rule.save_path {
+            params["save_path"] = json!(save_path);
+            def["savePath"] = json!(save_path);
+        }
+        def["torrentParams"] = params;
+
+        self.web
+            .post(
+                "rss/setRule",
+                &[("ruleName", &rule.name), ("ruleDef", &def.to_string())],
+            )
+            .await
+    }
+
+    async fn remove_rss_rule(&self, name: &str) -> anyhow::Result<()> {
+        self.web.post("rss/removeRule", &[("ruleName", name)]).await
+    }
+
+    /// Served from the `sync/maindata` cache of the previous poll.
+    async fn free_space(&self) -> anyhow::Result<Option<u64>> {
+        Ok(self.sync.lock().await.free_space)
+    }
+
+    /// qBittorrent 4.x only supports `torrents/pause`.
+    async fn pause_torrents(&self, hashes: &[String]) -> anyhow::Result<()> {
+        if self.is_legacy_api().await? {
+            return self
+                .web
+                .post("torrents/pause", &[("hashes", &hashes.join("|"))])
+                .await;
+        }
+
+        self.endpoint.stop_torrents(hashes.to_vec()).await?;
+        Ok(())
+    }
+
+    /// qBittorrent 4.x only supports `torrents/resume`.
+    async fn resume_torrents(&self, hashes: &[String]) -> anyhow::Result<()> {
+        if self.is_legacy_api().await? {
+            return self
+                .web
+                .post("torrents/resume", &[("hashes", &hashes.join("|"))])
+                .await;
+        }
+
+        self.endpoint.start_torrents(hashes.to_vec()).await?;
+        Ok(())
+    }
+
+    /// Served from the `sync/maindata` cache of the previous poll.
+    async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>> {
+        let sync = self.sync.lock().await;
+        if sync.rid == 0 {
+            return Err(AniplerDaemonError::InvalidQBitApiResponse(
+                "Torrent list is not synchronized yet".to_string(),
+            )
+            .into());
+        }
+
+        Ok(sync.torrents.keys().cloned().collect())
+    }
+}
+
+impl WebApiClient {
+    /// Post a form to a Web API endpoint, logging in again once if the
+    /// session expired.
+    async fn post(&self, endpoint: &str, form: &[(&str, &str)]) -> anyhow::Result<()> {
+        let mut response = self.post_once(endpoint, form).await?;
+        if response.status() == StatusCode::FORBIDDEN {
+            self.login().await?;
+            response = self.post_once(endpoint, form).await?;
+        }
+        response.error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn post_once(
+        &self,
+        endpoint: &str,
+        form: &[(&str, &str)],
+    ) -> anyhow::Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(self.url.join(endpoint)?)
+            .form(form)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn login(&self) -> anyhow::Result<()> {
+        tracing::debug!("Logging in to qBittorrent Web API");
+
+        let text = self
+            .post_once(
+                "auth/login",
+                &[("username", &self.username), ("password", &self.password)],
+            )
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        if text.trim() != "Ok." {
+            anyhow::bail!("qBittorrent login failed");
+        }
+
+        Ok(())
+    }
+}
+
+impl SyncState {
+    /// Forget all cached state so that the next poll requests a full update.
+    fn reset(&mut self) {
+        self.rid = 0;
+        self.torrents.clear();
+        self.free_space = None;
+    }
+
+    /// Merge a `sync/maindata` response into the cache.
+    ///
+    /// Returns hashes of torrents added or changed by this response.
+    fn apply(&mut self, data: SyncData) -> Vec<String> {
+        if data.full_update.unwrap_or(false) {
+            tracing::debug!("Received full update from qBittorrent");
+            self.torrents.clear();
+        }
+
+        for hash in data.torrents_removed.unwrap_or_default() {
+            self.torrents.remove(&hash);
+        }
+
+        let mut changed = Vec::new();
+        for (hash, delta) in data.torrents.unwrap_or_default() {
+            match self.torrents.entry(hash.clone()) {
+                Entry::Occupied(mut entry) => merge_torrent(entry.get_mut(), delta),
+                Entry::Vacant(entry) => {
+                    entry.insert(delta);
+                }
+            }
+            changed.push(hash);
+        }
+
+        if let Some(free_space) = data
+            .server_state
+            .and_then(|state| state.get("free_space_on_disk").cloned())
+        {
+            self.free_space = free_space.deserialize_into::<u64>().ok();
+        }
+
+        self.rid = data.rid;
+
+        changed
+    }
+}
+
+/// Apply changed fields of a partial torrent object from `sync/maindata`.
+///
+/// Only fields read by anipler are merged.
+fn merge_torrent(cached: &mut Torrent, delta: Torrent) {
+    macro_rules! merge_fields {
+        ($($field:ident),+ $(,)?) => {
+            $(
+                if delta.$field.is_some() {
+                    cached.$field = delta.$field;
+                }
+            )+
+        };
+    }
+
+    merge_fields!(
+        name,
+        progress,
+        content_path,
+        added_on,
+        tags,
+        category,
+        size,
+        eta,
+        dlspeed,
+        num_seeds,
+        state,
+        tracker,
+        save_path,
+        completion_on,
+    );
+}
+
+/// Whether a Web API version such as `2.9.3` predates qBittorrent 5.
+///
+/// Unparsable versions are assumed to be recent.
+fn is_legacy_api_version(version: &str) -> bool {
+    let mut parts = version.trim().split('.').map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor) < QBIT_5_API_VERSION,
+        _ => false,
+    }
+}
+
+/// Tracked tag of a torrent, `tags` is a comma separated list.
+fn tracked_tag(tracked: &[String], t: &Torrent) -> Option<String> {
+    let tags = t
+        .tags
+        .as_deref()?
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    matching_tag(tracked, &tags)
+}
+
+/// Convert a qBittorrent torrent into task info, along with its `added_on` timestamp.
+fn torrent_task_info(
+    t: Torrent,
+    tag: String,
+) -> Result<(TorrentTaskInfo, i64), AniplerDaemonError> {
+    macro_rules! extract_filed {
+        ($opt:expr, $field:expr) => {{
+            let Some(value) = $opt else {
+                return Err(AniplerDaemonError::InvalidQBitApiResponse(format!(
+                    "Missing field {} in torrent info",
+                    $field
+                )));
+            };
+            value
+        }};
+    }
+
+    let hash = extract_filed!(t.hash, "hash");
+    let progress = extract_filed!(t.progress, "progress");
+    // Keep the Web API spelling, e.g. `stalledDL`.
+    let state = t
+        .state
+        .and_then(|state| serde_json::to_value(state).ok())
+        .and_then(|state| state.as_str().map(ToString::to_string));
+    let status = match state.as_deref() {
+        Some("error") => TorrentStatus::Errored,
+        Some("missingFiles") => TorrentStatus::MissingFiles,
+        Some("metaDL" | "forcedMetaDL") => TorrentStatus::FetchingMetadata,
+        Some("stalledDL") => TorrentStatus::Stalled,
+        _ if progress < 1.0 => TorrentStatus::Downloading,
+        _ => TorrentStatus::Seeding,
+    };
+    let content_path = extract_filed!(t.content_path, "content_path");
+    let name = extract_filed!(t.name, "name");
+    let added_on = extract_filed!(t.added_on, "added_on");
+    // Uncategorized torrents report an empty category.
+    let category = t.category.filter(|category| !category.is_empty());
+    let stats = TorrentStats {
+        size: t.size,
+        progress,
+        eta: t.eta,
+        dlspeed: t.dlspeed,
+        num_seeds: t.num_seeds,
+        state,
+        // Not yet completed torrents report -1.
+        completion_on: t.completion_on.filter(|&completion_on| completion_on > 0),
+    };
+
+    let info = TorrentTaskInfo {
+        hash,
+        status,
+        content_path,
+        name,
+        tag,
+        category,
+        save_path: t.save_path,
+        stats,
+        seedbox: String::new(),
+    };
+
+    Ok((info, added_on))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_api_is_detected_by_web_api_version() {
+        assert!(is_legacy_api_version("2.8.3"));
+        assert!(is_legacy_api_version("2.10.4\n"));
+        assert!(!is_legacy_api_version("2.11.2"));
+        assert!(!is_legacy_api_version("3.0"));
+        assert!(!is_legacy_api_version("unknown"));
+    }
+
+    fn sync_data(value: serde_json::Value) -> SyncData {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn full_update_resets_cached_torrents() {
+        let mut state = SyncState::default();
+        state.apply(sync_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": { "aaaa": { "name": "Old", "progress": 1.0 } },
+        })));
+
+        let changed = state.apply(sync_data(json!({
+            "rid": 2,
+            "full_update": true,
+            "torrents": { "bbbb": { "name": "New", "progress": 0.5 } },
+            "server_state": { "free_space_on_disk": 1024 },
+        })));
+
+        assert_eq!(changed, ["bbbb"]);
+        assert_eq!(state.rid, 2);
+        assert_eq!(state.torrents.keys().collect::<Vec<_>>(), ["bbbb"]);
+        assert_eq!(state.free_space, Some(1024));
+    }
+
+    #[test]
+    fn partial_update_merges_changed_fields() {
+        let mut state = SyncState::default();
+        state.apply(sync_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": {
+                "aaaa": { "name": "Show", "progress": 0.5, "tags": "anipler", "size": 100 },
+            },
+        })));
+
+        let changed = state.apply(sync_data(json!({
+            "rid": 2,
+            "torrents": { "aaaa": { "progress": 1.0, "completion_on": 1000 } },
+        })));
+
+        assert_eq!(changed, ["aaaa"]);
+        let torrent = &state.torrents["aaaa"];
+        assert_eq!(torrent.name.as_deref(), Some("Show"));
+        assert_eq!(torrent.tags.as_deref(), Some("anipler"));
+        assert_eq!(torrent.size, Some(100));
+        assert_eq!(torrent.progress, Some(1.0));
+        assert_eq!(torrent.completion_on, Some(1000));
+    }
+
+    #[test]
+    fn removed_torrents_are_dropped_from_cache() {
+        let mut state = SyncState::default();
+        state.apply(sync_data(json!({
+            "rid": 1,
+            "full_update": true,
+            "torrents": { "aaaa": { "name": "A" }, "bbbb": { "name": "B" } },
+        })));
+
+        let changed = state.apply(sync_data(json!({
+            "rid": 2,
+            "torrents_removed": ["aaaa"],
+        })));
+
+        assert!(changed.is_empty());
+        assert_eq!(state.torrents.keys().collect::<Vec<_>>(), ["bbbb"]);
+    }
+
+    #[tokio::test]
+    async fn reset_sync_requests_full_update() {
+        let seedbox = QBitSeedbox::from_config(
+            &QBitConfig {
+                url: Url::parse("http://localhost:8080").unwrap(),
+                username: "admin".to_string(),
+                password: "password".to_string(),
+            },
+            vec!["anipler".to_string()],
+        )
+        .unwrap();
+        seedbox.sync.lock().await.apply(sync_data(json!({
+            "rid": 3,
+            "full_update": true,
+            "torrents": { "aaaa": { "name": "A" } },
+        })));
+
+        seedbox.reset_sync().await;
+
+        let sync = seedbox.sync.lock().await;
+        assert_eq!(sync.rid, 0);
+        assert!(sync.torrents.is_empty());
+    }
 }
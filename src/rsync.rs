@@ -2,10 +2,16 @@
 
 use std::collections::{HashMap, HashSet};
 
+use std::process::{ExitStatus, Stdio};
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::{Mutex, Semaphore, SemaphorePermit, TryAcquireError};
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit, TryAcquireError, watch};
+use tokio_util::sync::CancellationToken;
 
 use crate::config::DaemonConfig;
+use crate::sftp::{SftpExecutor, SftpTransferError};
 use crate::task::TransferTaskInfo;
 
 /// Executes rsync transfer tasks from seedbox to relay with transfer de-duplication.
@@ -16,8 +22,14 @@ use crate::task::TransferTaskInfo;
 /// - Keep transfer orchestration simple in daemon code.
 /// - Preserve compatibility with future multi-session scheduling.
 ///
-/// Currently we only allow one session at a time to avoid overloading the
-/// bandwidth of home network.
+/// Only one regular session runs at a time. Within it, up to
+/// `transfer.max_concurrent` transfers execute in parallel, one by default to
+/// avoid overloading the bandwidth of home network.
+///
+/// Priority sessions, requested by the user for specific torrents, bypass the
+/// session gate. Their transfers wait for the next free transfer slot ahead
+/// of the remaining tasks of a running session, without interrupting transfers
+/// already in flight.
 ///
 /// # Deliberate behavior
 ///
@@ -28,10 +40,17 @@ use crate::task::TransferTaskInfo;
 ///
 /// # Concurrency model
 ///
-/// - Session acquisition is currently guarded by a semaphore permit of capacity 1.
+/// - Regular session acquisition is guarded by a semaphore permit of capacity 1.
+/// - Transfer execution is bounded by a second semaphore of capacity
+///   `transfer.max_concurrent`, shared by all sessions. Tokio semaphores are
+///   fair, so a priority transfer waiting for a slot is served before tasks
+///   a regular session starts afterwards.
 /// - Creating a session records planned hashes in `queued_counts`.
 /// - Per-hash `try_guard(hash)` is non-blocking and grants exclusive execution.
 /// - `TransferGuard` marks hash as in-progress; caller must invoke `release()`.
+/// - Each in-progress hash has a cancellation token. Cancelling it aborts the
+///   transfer, killing the rsync process, and the guard's `transfer` returns
+///   [`RsyncTransmitterError::Cancelled`].
 /// - Releasing `RsyncTransferSession` decrements queued counts for all hashes
 ///   planned by that session.
 ///
@@ -42,18 +61,40 @@ use crate::task::TransferTaskInfo;
 /// Correctness relies on storage transitions being concurrency-safe and
 /// atomic.
 pub struct RsyncTransmitter {
-    executor: RsyncExecutor,
+    executor: TransferExecutor,
     gate: Semaphore,
+    slots: Semaphore,
     tracker: TransferTracker,
 }
 
+/// Transfer implementation selected by `transfer.backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferBackend {
+    /// Spawn `rsync` over `ssh`.
+    #[default]
+    Rsync,
+    /// Download natively over SFTP.
+    Sftp,
+}
+
+enum TransferExecutor {
+    Rsync(RsyncExecutor),
+    Sftp(SftpExecutor),
+}
+
 struct RsyncExecutor {
-    ssh_host: String,
-    ssh_key_path: String,
+    /// SSH connection of each seedbox by name.
+    hosts: HashMap<String, SshTarget>,
     speed_limit: Option<u32>,
     dry_run: bool,
 }
 
+struct SshTarget {
+    host: String,
+    key_path: String,
+}
+
 struct TransferTracker {
     state: Mutex<TransferTrackerState>,
 }
@@ -61,12 +102,20 @@ struct TransferTracker {
 #[derive(Default)]
 struct TransferTrackerState {
     queued_counts: HashMap<String, usize>,
-    in_progress: HashSet<String>,
+    in_progress: HashMap<String, ActiveTransfer>,
+}
+
+/// Tracker state of an in-progress hash.
+struct ActiveTransfer {
+    progress: watch::Receiver<Option<TransferProgress>>,
+    cancel: CancellationToken,
 }
 
 pub struct RsyncTransferSession<'a> {
-    _permit: SemaphorePermit<'a>,
-    executor: &'a RsyncExecutor,
+    /// Session gate permit, `None` for priority sessions.
+    _permit: Option<SemaphorePermit<'a>>,
+    executor: &'a TransferExecutor,
+    slots: &'a Semaphore,
     tracker: &'a TransferTracker,
     task_hashes: HashSet<String>,
     tasks: Vec<TransferTaskInfo>,
@@ -83,7 +132,10 @@ pub struct RsyncTransferSession<'a> {
 pub struct TransferGuard<'a> {
     hash: String,
     tracker: &'a TransferTracker,
-    executor: &'a RsyncExecutor,
+    executor: &'a TransferExecutor,
+    slots: &'a Semaphore,
+    progress: watch::Sender<Option<TransferProgress>>,
+    cancel: CancellationToken,
     released: bool,
 }
 
@@ -94,14 +146,40 @@ pub enum TransferState {
     Ongoing,
 }
 
+/// Overall progress of an ongoing transfer, as reported by rsync
+/// `--info=progress2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct TransferProgress {
+    /// Bytes transferred so far.
+    pub bytes: u64,
+    /// Percentage of the whole transfer done.
+    pub percent: u8,
+    /// Current transfer rate in bytes per second.
+    pub bytes_per_sec: u64,
+    /// Estimated remaining time in seconds.
+    pub eta: i64,
+}
+
 impl RsyncTransmitter {
     /// Build a transmitter from daemon configuration.
     pub fn from_config(config: &DaemonConfig) -> Self {
-        tracing::debug!(host = %config.seedbox.ssh_host, dry_run = config.transfer.is_dry_run(), "Creating rsync transmitter");
+        tracing::debug!(
+            seedboxes = config.seedboxes.len(),
+            backend = ?config.transfer.backend,
+            max_concurrent = config.transfer.max_concurrent,
+            dry_run = config.transfer.is_dry_run(),
+            "Creating rsync transmitter"
+        );
+
+        let executor = match config.transfer.backend {
+            TransferBackend::Rsync => TransferExecutor::Rsync(RsyncExecutor::from_config(config)),
+            TransferBackend::Sftp => TransferExecutor::Sftp(SftpExecutor::from_config(config)),
+        };
 
         Self {
-            executor: RsyncExecutor::from_config(config),
+            executor,
             gate: Semaphore::new(1),
+            slots: Semaphore::new(config.transfer.max_concurrent),
             tracker: TransferTracker::new(),
         }
     }
@@ -122,7 +200,7 @@ impl RsyncTransmitter {
             .await
             .map_err(|_| RsyncTransmitterError::SemaphoreClosed)?;
 
-        Ok(self.make_session(tasks, permit).await)
+        Ok(self.make_session(tasks, Some(permit)).await)
     }
 
     /// Try to acquire a transfer session without blocking.
@@ -140,14 +218,23 @@ impl RsyncTransmitter {
             TryAcquireError::NoPermits => RsyncTransmitterError::OverlappingTransfer,
         })?;
 
-        Ok(self.make_session(tasks, permit).await)
+        Ok(self.make_session(tasks, Some(permit)).await)
+    }
+
+    /// Create a priority session that runs alongside the regular session.
+    ///
+    /// Its transfers still respect `transfer.max_concurrent`, and take the
+    /// next free transfer slot ahead of tasks the regular session has yet to
+    /// start.
+    pub async fn priority_session(&self, tasks: Vec<TransferTaskInfo>) -> RsyncTransferSession<'_> {
+        self.make_session(tasks, None).await
     }
 
     /// Create a transfer session and register its planned hashes as queued.
     pub async fn make_session<'a>(
         &'a self,
         tasks: Vec<TransferTaskInfo>,
-        permit: SemaphorePermit<'a>,
+        permit: Option<SemaphorePermit<'a>>,
     ) -> RsyncTransferSession<'a> {
         self.tracker
             .enqueue_many(tasks.iter().map(|task| task.hash.as_str()))
@@ -157,6 +244,7 @@ impl RsyncTransmitter {
         RsyncTransferSession {
             _permit: permit,
             executor: &self.executor,
+            slots: &self.slots,
             tracker: &self.tracker,
             task_hashes,
             tasks,
@@ -168,6 +256,28 @@ impl RsyncTransmitter {
     pub async fn transfer_state(&self, hash: &str) -> TransferState {
         self.tracker.state_of(hash).await
     }
+
+    /// Return the latest progress of an ongoing transfer, `None` if it is not
+    /// running or has not reported progress yet.
+    pub async fn transfer_progress(&self, hash: &str) -> Option<TransferProgress> {
+        self.tracker.progress_of(hash).await
+    }
+
+    /// Cancel the ongoing transfer whose hash starts with `prefix`.
+    ///
+    /// Returns the full hash of the cancelled transfer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no or several ongoing transfers match the prefix.
+    pub async fn cancel(&self, prefix: &str) -> Result<String, RsyncTransmitterError> {
+        self.tracker.cancel(prefix).await
+    }
+
+    /// List hashes of all ongoing transfers along with their latest progress.
+    pub async fn ongoing_transfers(&self) -> Vec<(String, Option<TransferProgress>)> {
+        self.tracker.ongoing().await
+    }
 }
 
 impl RsyncTransferSession<'_> {
@@ -192,14 +302,17 @@ impl RsyncTransferSession<'_> {
             });
         }
 
-        if !self.tracker.try_start(hash).await? {
+        let Some((progress, cancel)) = self.tracker.try_start(hash).await? else {
             return Ok(None);
-        }
+        };
 
         Ok(Some(TransferGuard {
             hash: hash.to_string(),
             tracker: self.tracker,
             executor: self.executor,
+            slots: self.slots,
+            progress,
+            cancel,
             released: false,
         }))
     }
@@ -231,12 +344,41 @@ impl Drop for RsyncTransferSession<'_> {
 }
 
 impl TransferGuard<'_> {
-    /// Execute rsync while this guard is held.
+    /// Execute rsync while this guard is held, waiting for a free transfer
+    /// slot if `transfer.max_concurrent` transfers are running.
     ///
     /// Callers should perform status check and storage state transition under
     /// the same guard lifetime to avoid re-transfer races.
-    pub async fn transfer(&self, task: &TransferTaskInfo) -> Result<(), RsyncTransmitterError> {
-        self.executor.transfer(task).await
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the slot semaphore is closed, the transfer fails,
+    /// or it is cancelled.
+    pub async fn transfer(
+        &self,
+        task: &TransferTaskInfo,
+        manifest: Option<&[String]>,
+    ) -> Result<(), RsyncTransmitterError> {
+        let transfer = async {
+            let _slot = self
+                .slots
+                .acquire()
+                .await
+                .map_err(|_| RsyncTransmitterError::SemaphoreClosed)?;
+
+            self.executor.transfer(task, manifest, &self.progress).await
+        };
+
+        // Dropping the transfer future kills the rsync process.
+        tokio::select! {
+            result = transfer => result,
+            () = self.cancel.cancelled() => {
+                tracing::info!(hash = %self.hash, "Transfer cancelled");
+                Err(RsyncTransmitterError::Cancelled {
+                    hash: self.hash.clone(),
+                })
+            }
+        }
     }
 
     /// Release in-progress ownership for this hash.
@@ -281,8 +423,15 @@ impl TransferTracker {
 
     /// Try to mark a queued hash as in-progress.
     ///
-    /// Returns `Ok(false)` if already in progress in another session.
-    async fn try_start(&self, hash: &str) -> Result<bool, RsyncTransmitterError> {
+    /// Returns the sender for progress updates and the cancellation token of
+    /// the transfer, or `Ok(None)` if already in progress in another session.
+    async fn try_start(
+        &self,
+        hash: &str,
+    ) -> Result<
+        Option<(watch::Sender<Option<TransferProgress>>, CancellationToken)>,
+        RsyncTransmitterError,
+    > {
         let mut state = self.lock_state().await;
         if !state.queued_counts.contains_key(hash) {
             return Err(RsyncTransmitterError::UnclaimedHash {
@@ -290,13 +439,21 @@ impl TransferTracker {
             });
         }
 
-        if state.in_progress.contains(hash) {
-            return Ok(false);
+        if state.in_progress.contains_key(hash) {
+            return Ok(None);
         }
 
-        state.in_progress.insert(hash.to_string());
+        let (progress, receiver) = watch::channel(None);
+        let cancel = CancellationToken::new();
+        state.in_progress.insert(
+            hash.to_string(),
+            ActiveTransfer {
+                progress: receiver,
+                cancel: cancel.clone(),
+            },
+        );
         drop(state);
-        Ok(true)
+        Ok(Some((progress, cancel)))
     }
 
     /// Remove a hash from in-progress set, marking transfer completion.
@@ -307,7 +464,7 @@ impl TransferTracker {
     /// Returns current transfer state for a hash.
     async fn state_of(&self, hash: &str) -> TransferState {
         let state = self.lock_state().await;
-        if state.in_progress.contains(hash) {
+        if state.in_progress.contains_key(hash) {
             TransferState::Ongoing
         } else if state.queued_counts.contains_key(hash) {
             TransferState::Queued
@@ -316,6 +473,43 @@ impl TransferTracker {
         }
     }
 
+    /// Returns latest progress of an in-progress hash.
+    async fn progress_of(&self, hash: &str) -> Option<TransferProgress> {
+        self.lock_state()
+            .await
+            .in_progress
+            .get(hash)
+            .and_then(|transfer| *transfer.progress.borrow())
+    }
+
+    /// Returns all in-progress hashes with their latest progress.
+    async fn ongoing(&self) -> Vec<(String, Option<TransferProgress>)> {
+        self.lock_state()
+            .await
+            .in_progress
+            .iter()
+            .map(|(hash, transfer)| (hash.clone(), *transfer.progress.borrow()))
+            .collect()
+    }
+
+    /// Cancel the single in-progress hash starting with `prefix`.
+    async fn cancel(&self, prefix: &str) -> Result<String, RsyncTransmitterError> {
+        let prefix = prefix.to_lowercase();
+        let state = self.lock_state().await;
+        let mut matches = state
+            .in_progress
+            .iter()
+            .filter(|(hash, _)| hash.starts_with(&prefix));
+        match (matches.next(), matches.next()) {
+            (Some((hash, transfer)), None) => {
+                transfer.cancel.cancel();
+                Ok(hash.clone())
+            }
+            (None, _) => Err(RsyncTransmitterError::NoOngoingTransfer { prefix }),
+            (Some(_), Some(_)) => Err(RsyncTransmitterError::AmbiguousTransfer { prefix }),
+        }
+    }
+
     /// Lock tracker state.
     async fn lock_state(&self) -> tokio::sync::MutexGuard<'_, TransferTrackerState> {
         self.state.lock().await
@@ -335,13 +529,46 @@ impl TransferTrackerState {
     }
 }
 
+impl TransferExecutor {
+    /// Execute a single transfer task with the selected backend.
+    ///
+    /// Only rsync reports progress.
+    async fn transfer(
+        &self,
+        task: &TransferTaskInfo,
+        manifest: Option<&[String]>,
+        progress: &watch::Sender<Option<TransferProgress>>,
+    ) -> Result<(), RsyncTransmitterError> {
+        match self {
+            Self::Rsync(executor) => executor.transfer(task, manifest, progress).await,
+            Self::Sftp(executor) => executor.transfer(task, manifest).await.map_err(|source| {
+                RsyncTransmitterError::SftpFailed {
+                    dest: task.dest.clone(),
+                    source: Box::new(source),
+                }
+            }),
+        }
+    }
+}
+
 impl RsyncExecutor {
     /// Build rsync executor from daemon configuration.
     pub fn from_config(config: &DaemonConfig) -> Self {
-        tracing::debug!(host = %config.seedbox.ssh_host, dry_run = config.transfer.is_dry_run(), "Creating rsync transmitter");
+        let hosts = config
+            .seedboxes
+            .iter()
+            .map(|seedbox| {
+                tracing::debug!(seedbox = %seedbox.name, host = %seedbox.ssh_host, "Adding rsync source host");
+                let target = SshTarget {
+                    host: seedbox.ssh_host.clone(),
+                    key_path: seedbox.ssh_key.to_string_lossy().to_string(),
+                };
+                (seedbox.name.clone(), target)
+            })
+            .collect();
+
         Self {
-            ssh_host: config.seedbox.ssh_host.clone(),
-            ssh_key_path: config.seedbox.ssh_key.to_string_lossy().to_string(),
+            hosts,
             speed_limit: config.transfer.speed_limit,
             dry_run: config.transfer.is_dry_run(),
         }
@@ -349,14 +576,32 @@ impl RsyncExecutor {
 
     /// Execute a single rsync transfer task.
     ///
+    /// With a `manifest`, only the listed files are transferred. They are
+    /// relative to the save path of the torrent, so the artifact keeps the
+    /// same layout as a full transfer.
+    ///
+    /// Overall progress is published to `progress` while rsync runs.
+    ///
     /// # Errors
     ///
     /// Returns an error when rsync execution fails or exits unsuccessfully.
-    pub async fn transfer(&self, task: &TransferTaskInfo) -> Result<(), RsyncTransmitterError> {
-        let source = task.source.as_str();
+    pub async fn transfer(
+        &self,
+        task: &TransferTaskInfo,
+        manifest: Option<&[String]>,
+        progress: &watch::Sender<Option<TransferProgress>>,
+    ) -> Result<(), RsyncTransmitterError> {
         let dest = task.dest.as_str();
+        let target =
+            self.hosts
+                .get(&task.seedbox)
+                .ok_or_else(|| RsyncTransmitterError::UnknownSeedbox {
+                    seedbox: task.seedbox.clone(),
+                })?;
+        let source = rsync_source(task, manifest.is_some())?;
+        let source = source.as_str();
 
-        tracing::info!(source = %source, dest = %dest, "Transferring files");
+        tracing::info!(seedbox = %task.seedbox, source = %source, dest = %dest, "Transferring files");
 
         let mut rsync_cmd = Command::new("rsync");
         rsync_cmd.args([
@@ -364,16 +609,25 @@ impl RsyncExecutor {
             "--partial",
             "--recursive",
             "-s", // `--protect-args` / `--secluded-args`, use short version for compatibility.
+            "--info=progress2",
+            // Scan the whole file list first so that progress covers all files.
+            "--no-inc-recursive",
         ]);
 
-        if let Some(limit) = self.speed_limit {
+        if let Some(limit) = task.speed_limit.or(self.speed_limit) {
             rsync_cmd.arg("--bwlimit").arg(limit.to_string());
         }
 
+        if let Some(files) = manifest {
+            tracing::debug!(count = files.len(), "Transferring selected files");
+            // NUL separated list read from stdin, implies `--relative`.
+            rsync_cmd.args(["--from0", "--files-from=-"]);
+        }
+
         let ssh_cmd = shell_words::join([
             "ssh",
             "-i",
-            &self.ssh_key_path,
+            &target.key_path,
             "-o",
             "StrictHostKeyChecking=no",
             "-o",
@@ -382,7 +636,7 @@ impl RsyncExecutor {
         rsync_cmd.arg("--rsh").arg(ssh_cmd);
 
         // Because we use `-s`, no need to escape manually.
-        let source_arg = &format!("{}:{}", self.ssh_host, source);
+        let source_arg = &format!("{}:{}", target.host, source);
         rsync_cmd.arg(source_arg);
         rsync_cmd.arg(dest);
 
@@ -392,21 +646,26 @@ impl RsyncExecutor {
             return Ok(());
         }
 
-        rsync_cmd
-            .output()
+        run_rsync(rsync_cmd, manifest, progress)
             .await
-            .map_err(|e| RsyncTransmitterError::RsyncFailed {
-                dest: dest.to_string(),
-                reason: format!("failed to execute rsync command: {e}"),
+            .map_err(|e| match e {
+                RunRsyncError::Spawn(source) => RsyncTransmitterError::RsyncSpawn {
+                    dest: dest.to_string(),
+                    source,
+                },
+                RunRsyncError::Io(source) => RsyncTransmitterError::RsyncIo {
+                    dest: dest.to_string(),
+                    source,
+                },
             })
-            .and_then(|output| {
-                if output.status.success() {
-                    Ok(output)
+            .and_then(|(status, stderr)| {
+                if status.success() {
+                    Ok(())
                 } else {
-                    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                    Err(RsyncTransmitterError::RsyncFailed {
+                    Err(RsyncTransmitterError::RsyncExited {
                         dest: dest.to_string(),
-                        reason: format!("rsync exited with code {}: {}", output.status, stderr),
+                        code: RsyncExitCode::from_status(status),
+                        stderr: stderr.trim().to_string(),
                     })
                 }
             })?;
@@ -417,14 +676,435 @@ impl RsyncExecutor {
     }
 }
 
+/// Source path of an rsync transfer, the directory manifest files are relative
+/// to when only selected files are transferred.
+fn rsync_source(
+    task: &TransferTaskInfo,
+    with_manifest: bool,
+) -> Result<String, RsyncTransmitterError> {
+    if !with_manifest {
+        return Ok(task.source.clone());
+    }
+
+    let root = task
+        .manifest_root()
+        .ok_or_else(|| RsyncTransmitterError::RsyncFailed {
+            dest: task.dest.clone(),
+            reason: format!("source {} has no parent directory", task.source),
+        })?;
+    Ok(format!("{root}/"))
+}
+
+/// Failure to run rsync to completion, as opposed to rsync exiting with an error.
+enum RunRsyncError {
+    /// rsync could not be started at all.
+    Spawn(std::io::Error),
+    /// Talking to the running rsync process failed.
+    Io(std::io::Error),
+}
+
+/// Run rsync to completion, feeding the manifest to its stdin if given and
+/// publishing progress parsed from its output.
+///
+/// Returns the exit status along with the captured stderr.
+async fn run_rsync(
+    mut rsync_cmd: Command,
+    manifest: Option<&[String]>,
+    progress: &watch::Sender<Option<TransferProgress>>,
+) -> Result<(ExitStatus, String), RunRsyncError> {
+    let stdin = if manifest.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    let mut child = rsync_cmd
+        .kill_on_drop(true)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(RunRsyncError::Spawn)?;
+
+    // SAFETY: stdout and stderr are piped above.
+    let stdout = child.stdout.take().expect("rsync stdout is piped");
+    let mut stderr = child.stderr.take().expect("rsync stderr is piped");
+    let stdin = child.stdin.take();
+
+    let write_manifest = async {
+        if let (Some(files), Some(mut stdin)) = (manifest, stdin) {
+            let mut input = Vec::new();
+            for file in files {
+                input.extend_from_slice(file.as_bytes());
+                input.push(0);
+            }
+            match stdin.write_all(&input).await {
+                // rsync exited early, e.g. the SSH connection dropped. Its exit
+                // status tells more than the broken pipe.
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+            // Close stdin so that rsync sees the end of the file list.
+            drop(stdin);
+        }
+        Ok::<_, std::io::Error>(())
+    };
+
+    let read_progress = async {
+        // Progress lines are terminated by `\r` while rsync updates them.
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        while reader.read_until(b'\r', &mut line).await? > 0 {
+            for line in String::from_utf8_lossy(&line).lines() {
+                if let Some(update) = parse_progress(line) {
+                    progress.send_replace(Some(update));
+                }
+            }
+            line.clear();
+        }
+        Ok::<_, std::io::Error>(())
+    };
+
+    let read_stderr = async {
+        let mut output = Vec::new();
+        stderr.read_to_end(&mut output).await?;
+        Ok::<_, std::io::Error>(String::from_utf8_lossy(&output).to_string())
+    };
+
+    let ((), (), stderr) =
+        tokio::try_join!(write_manifest, read_progress, read_stderr).map_err(RunRsyncError::Io)?;
+    let status = child.wait().await.map_err(RunRsyncError::Io)?;
+
+    Ok((status, stderr))
+}
+
+/// Parse an `--info=progress2` line such as
+/// `1,234,567  45%   10.23MB/s    0:00:12 (xfr#1, to-chk=3/5)`.
+fn parse_progress(line: &str) -> Option<TransferProgress> {
+    let mut fields = line.split_whitespace();
+    let bytes = fields.next()?.replace(',', "").parse().ok()?;
+    let percent = fields.next()?.strip_suffix('%')?.parse().ok()?;
+    let bytes_per_sec = parse_rate(fields.next()?)?;
+    let eta = fields.next()?.split(':').try_fold(0, |total: i64, part| {
+        part.parse::<i64>().ok().map(|value| total * 60 + value)
+    })?;
+
+    Some(TransferProgress {
+        bytes,
+        percent,
+        bytes_per_sec,
+        eta,
+    })
+}
+
+/// Parse a transfer rate such as `10.23MB/s`, with binary unit prefixes.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn parse_rate(rate: &str) -> Option<u64> {
+    let rate = rate.strip_suffix("B/s")?;
+    let (value, multiplier) = match rate.char_indices().last()? {
+        (i, 'k' | 'K') => (&rate[..i], 1u64 << 10),
+        (i, 'M') => (&rate[..i], 1 << 20),
+        (i, 'G') => (&rate[..i], 1 << 30),
+        (i, 'T') => (&rate[..i], 1 << 40),
+        _ => (rate, 1),
+    };
+    let value = value.replace(',', "").parse::<f64>().ok()?;
+
+    Some((value * multiplier as f64) as u64)
+}
+
+/// Reason of an unsuccessful rsync run, by its documented exit code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RsyncExitCode {
+    /// 1: syntax or usage error.
+    Syntax,
+    /// 2: protocol incompatibility.
+    ProtocolIncompatible,
+    /// 3: errors selecting input/output files or dirs.
+    FileSelection,
+    /// 4: requested action not supported.
+    Unsupported,
+    /// 5: error starting client-server protocol.
+    Startup,
+    /// 10: error in socket I/O.
+    SocketIo,
+    /// 11: error in file I/O.
+    FileIo,
+    /// 12: error in rsync protocol data stream.
+    ProtocolStream,
+    /// 13: errors with program diagnostics.
+    Diagnostics,
+    /// 14: error in IPC code.
+    Ipc,
+    /// 20: received SIGUSR1 or SIGINT.
+    Interrupted,
+    /// 21: some error returned by `waitpid()`.
+    Waitpid,
+    /// 22: error allocating core memory buffers.
+    OutOfMemory,
+    /// 23: partial transfer due to error.
+    PartialTransfer,
+    /// 24: partial transfer due to vanished source files.
+    VanishedFiles,
+    /// 25: the `--max-delete` limit stopped deletions.
+    DeleteLimit,
+    /// 30: timeout in data send/receive.
+    DataTimeout,
+    /// 35: timeout waiting for daemon connection.
+    ConnectTimeout,
+    /// 255: the SSH connection failed.
+    Ssh,
+    /// Terminated by a signal without an exit code.
+    Killed,
+    /// Undocumented exit code.
+    Other(i32),
+}
+
+impl RsyncExitCode {
+    /// Map an rsync exit code.
+    pub const fn from_code(code: i32) -> Self {
+        match code {
+            1 => Self::Syntax,
+            2 => Self::ProtocolIncompatible,
+            3 => Self::FileSelection,
+            4 => Self::Unsupported,
+            5 => Self::Startup,
+            10 => Self::SocketIo,
+            11 => Self::FileIo,
+            12 => Self::ProtocolStream,
+            13 => Self::Diagnostics,
+            14 => Self::Ipc,
+            20 => Self::Interrupted,
+            21 => Self::Waitpid,
+            22 => Self::OutOfMemory,
+            23 => Self::PartialTransfer,
+            24 => Self::VanishedFiles,
+            25 => Self::DeleteLimit,
+            30 => Self::DataTimeout,
+            35 => Self::ConnectTimeout,
+            255 => Self::Ssh,
+            code => Self::Other(code),
+        }
+    }
+
+    /// Map the exit status of an unsuccessful rsync run.
+    fn from_status(status: ExitStatus) -> Self {
+        status.code().map_or(Self::Killed, Self::from_code)
+    }
+
+    /// Whether retrying the same transfer later may succeed, e.g. after a
+    /// network failure or while the seedbox is still rewriting files, as
+    /// opposed to failures needing user intervention such as a wrong command
+    /// line or an unsupported rsync version.
+    pub const fn is_transient(self) -> bool {
+        !matches!(
+            self,
+            Self::Syntax | Self::ProtocolIncompatible | Self::FileSelection | Self::Unsupported
+        )
+    }
+}
+
+impl std::fmt::Display for RsyncExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (code, text) = match self {
+            Self::Syntax => (1, "syntax or usage error"),
+            Self::ProtocolIncompatible => (2, "protocol incompatibility"),
+            Self::FileSelection => (3, "error selecting input/output files"),
+            Self::Unsupported => (4, "requested action not supported"),
+            Self::Startup => (5, "error starting client-server protocol"),
+            Self::SocketIo => (10, "socket I/O error"),
+            Self::FileIo => (11, "file I/O error"),
+            Self::ProtocolStream => (12, "protocol data stream error"),
+            Self::Diagnostics => (13, "program diagnostics error"),
+            Self::Ipc => (14, "IPC error"),
+            Self::Interrupted => (20, "interrupted"),
+            Self::Waitpid => (21, "waitpid error"),
+            Self::OutOfMemory => (22, "out of memory"),
+            Self::PartialTransfer => (23, "partial transfer due to error"),
+            Self::VanishedFiles => (24, "source files vanished"),
+            Self::DeleteLimit => (25, "deletion limit reached"),
+            Self::DataTimeout => (30, "data transfer timed out"),
+            Self::ConnectTimeout => (35, "connection timed out"),
+            Self::Ssh => (255, "SSH connection failed"),
+            Self::Killed => return f.write_str("killed by a signal"),
+            Self::Other(code) => return write!(f, "exit code {code}"),
+        };
+        write!(f, "{text} (exit code {code})")
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RsyncTransmitterError {
     #[error("semaphore closed")]
     SemaphoreClosed,
     #[error("Rsync command failed for {dest}: {reason}")]
     RsyncFailed { dest: String, reason: String },
+    #[error("Failed to execute rsync for {dest}: {source}")]
+    RsyncSpawn {
+        dest: String,
+        source: std::io::Error,
+    },
+    #[error("I/O error while running rsync for {dest}: {source}")]
+    RsyncIo {
+        dest: String,
+        source: std::io::Error,
+    },
+    #[error("rsync failed for {dest}: {code}: {stderr}")]
+    RsyncExited {
+        dest: String,
+        code: RsyncExitCode,
+        /// Captured standard error of rsync.
+        stderr: String,
+    },
     #[error("Another transfer job is already in progress")]
     OverlappingTransfer,
     #[error("Hash is not part of current transfer session: {hash}")]
     UnclaimedHash { hash: String },
+    #[error("No SSH host is configured for seedbox {seedbox}")]
+    UnknownSeedbox { seedbox: String },
+    #[error("Transfer of {hash} was cancelled")]
+    Cancelled { hash: String },
+    #[error("No ongoing transfer matches {prefix}")]
+    NoOngoingTransfer { prefix: String },
+    #[error("Several ongoing transfers match {prefix}")]
+    AmbiguousTransfer { prefix: String },
+    #[error("SFTP transfer failed for {dest}: {source}")]
+    SftpFailed {
+        dest: String,
+        source: Box<SftpTransferError>,
+    },
+}
+
+impl RsyncTransmitterError {
+    /// Whether retrying the transfer later may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RsyncExited { code, .. } => code.is_transient(),
+            Self::SftpFailed { source, .. } => source.is_transient(),
+            // rsync could not be spawned, or the task can not be mapped to a source.
+            Self::RsyncFailed { .. } | Self::RsyncSpawn { .. } | Self::UnknownSeedbox { .. } => {
+                false
+            }
+            Self::RsyncIo { .. }
+            | Self::SemaphoreClosed
+            | Self::OverlappingTransfer
+            | Self::UnclaimedHash { .. }
+            | Self::Cancelled { .. }
+            | Self::NoOngoingTransfer { .. }
+            | Self::AmbiguousTransfer { .. } => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress2_line_is_parsed() {
+        assert_eq!(
+            parse_progress("      1,234,567  45%   10.50MB/s    0:01:12 (xfr#1, to-chk=3/5)"),
+            Some(TransferProgress {
+                bytes: 1_234_567,
+                percent: 45,
+                bytes_per_sec: 10 * 1024 * 1024 + 512 * 1024,
+                eta: 72,
+            })
+        );
+        assert_eq!(
+            parse_progress("            512   0%    0.00kB/s    0:00:00").map(|p| p.bytes_per_sec),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn exit_codes_are_classified() {
+        assert_eq!(RsyncExitCode::from_code(23), RsyncExitCode::PartialTransfer);
+        assert_eq!(RsyncExitCode::from_code(255), RsyncExitCode::Ssh);
+        assert_eq!(RsyncExitCode::from_code(42), RsyncExitCode::Other(42));
+
+        for code in [10, 12, 23, 24, 25, 30, 35, 255] {
+            assert!(RsyncExitCode::from_code(code).is_transient(), "{code}");
+        }
+        for code in [1, 2, 3, 4] {
+            assert!(!RsyncExitCode::from_code(code).is_transient(), "{code}");
+        }
+
+        assert_eq!(
+            RsyncExitCode::from_code(30).to_string(),
+            "data transfer timed out (exit code 30)"
+        );
+    }
+
+    #[test]
+    fn partial_transfers_are_retried() {
+        let error = RsyncTransmitterError::RsyncExited {
+            dest: "/relay".to_string(),
+            code: RsyncExitCode::from_code(23),
+            stderr: "rsync error: some files/attrs were not transferred".to_string(),
+        };
+
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn only_runtime_io_errors_are_transient() {
+        assert!(
+            RsyncTransmitterError::RsyncIo {
+                dest: "/relay".to_string(),
+                source: std::io::Error::from(std::io::ErrorKind::BrokenPipe),
+            }
+            .is_transient()
+        );
+        assert!(
+            !RsyncTransmitterError::RsyncSpawn {
+                dest: "/relay".to_string(),
+                source: std::io::Error::from(std::io::ErrorKind::NotFound),
+            }
+            .is_transient()
+        );
+    }
+
+    fn transfer_task(source: &str, save_path: Option<&str>) -> TransferTaskInfo {
+        TransferTaskInfo {
+            hash: "hash".to_string(),
+            source: source.to_string(),
+            dest: "/relay/anipler".to_string(),
+            name: "Show".to_string(),
+            tag: "anipler".to_string(),
+            category: None,
+            speed_limit: None,
+            seedbox: "default".to_string(),
+            save_path: save_path.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn manifest_files_are_relative_to_the_save_path() {
+        // Single-file torrent, the content path is the file itself.
+        let task = transfer_task("/downloads/Show - 01.mkv", Some("/downloads/"));
+        assert_eq!(rsync_source(&task, true).unwrap(), "/downloads/");
+        assert_eq!(
+            rsync_source(&task, false).unwrap(),
+            "/downloads/Show - 01.mkv"
+        );
+
+        // Multi-file torrent without root folder, the content path is the save path.
+        let task = transfer_task("/downloads/anime", Some("/downloads/anime"));
+        assert_eq!(rsync_source(&task, true).unwrap(), "/downloads/anime/");
+
+        // Without a known save path, the parent of the content is assumed.
+        let task = transfer_task("/downloads/Show", None);
+        assert_eq!(rsync_source(&task, true).unwrap(), "/downloads/");
+    }
+
+    #[test]
+    fn non_progress_output_is_ignored() {
+        assert_eq!(parse_progress("receiving incremental file list"), None);
+        assert_eq!(parse_progress("Show/Show - 01.mkv"), None);
+        assert_eq!(parse_progress(""), None);
+    }
 }
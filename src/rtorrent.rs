@@ -0,0 +1,588 @@
+//! rTorrent XML-RPC seedbox backend, over SCGI or HTTP.
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use qbit_rs::model::TorrentSource;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use url::Url;
+
+use crate::{
+    config::RTorrentConfig,
+    seedbox::{Seedbox, matching_tag},
+    task::{TorrentStats, TorrentStatus, TorrentTaskInfo},
+    xmlrpc::{self, Value, XmlRpcError},
+};
+
+/// Custom key used to find freshly uploaded torrents, rTorrent does not report
+/// the hash of loaded torrents.
+const UPLOAD_MARKER_KEY: &str = "anipler_upload";
+const UPLOAD_LOOKUP_ATTEMPTS: u32 = 10;
+const UPLOAD_LOOKUP_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum RTorrentSeedboxError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("SCGI I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("XML-RPC error: {0}")]
+    XmlRpc(#[from] XmlRpcError),
+    #[error("rTorrent responded with an invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Unsupported rTorrent URL: {0}")]
+    UnsupportedUrl(String),
+}
+
+enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: Url,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    ScgiTcp(String),
+    #[cfg(unix)]
+    ScgiUnix(PathBuf),
+}
+
+/// Seedbox backend talking to rTorrent through XML-RPC.
+///
+/// rTorrent has no tags, anipler tracks torrents whose configured custom field
+/// (`d.custom1` by default, which is also the ruTorrent label) equals a
+/// tracked tag.
+pub struct RTorrentSeedbox {
+    transport: Transport,
+    tag_field: String,
+    /// Tracked tags in order of precedence.
+    tags: Vec<String>,
+    upload_counter: AtomicU64,
+}
+
+struct RTorrentTorrent {
+    hash: String,
+    name: String,
+    complete: bool,
+    base_path: String,
+    directory: String,
+    is_multi_file: bool,
+    load_date: i64,
+    tag: String,
+    upload_marker: String,
+}
+
+impl RTorrentSeedbox {
+    /// Create an rTorrent client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL scheme is neither `scgi` nor `http(s)`.
+    pub fn from_config(
+        config: &RTorrentConfig,
+        tags: Vec<String>,
+    ) -> Result<Self, RTorrentSeedboxError> {
+        tracing::debug!(endpoint = %config.url, "Creating rTorrent seedbox client");
+
+        let url = &config.url;
+        let transport = match url.scheme() {
+            "http" | "https" => Transport::Http {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+                username: config.username.clone(),
+                password: config.password.clone(),
+            },
+            "scgi" => match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) if !host.is_empty() => {
+                    Transport::ScgiTcp(format!("{host}:{port}"))
+                }
+                #[cfg(unix)]
+                (None | Some(""), None) => Transport::ScgiUnix(PathBuf::from(url.path())),
+                _ => return Err(RTorrentSeedboxError::UnsupportedUrl(url.to_string())),
+            },
+            _ => return Err(RTorrentSeedboxError::UnsupportedUrl(url.to_string())),
+        };
+
+        Ok(Self {
+            transport,
+            tag_field: config.tag_field.clone(),
+            tags,
+            upload_counter: AtomicU64::new(0),
+        })
+    }
+
+    async fn call(&self, method: &str, params: &[Value]) -> Result<Value, RTorrentSeedboxError> {
+        let body = xmlrpc::encode_call(method, params);
+
+        let response = match &self.transport {
+            Transport::Http {
+                client,
+                url,
+                username,
+                password,
+            } => {
+                let mut request = client
+                    .post(url.clone())
+                    .header("content-type", "text/xml")
+                    .body(body);
+                if let Some(username) = username {
+                    request = request.basic_auth(username, password.as_ref());
+                }
+                request.send().await?.error_for_status()?.text().await?
+            }
+            Transport::ScgiTcp(addr) => {
+                let stream = tokio::net::TcpStream::connect(addr).await?;
+                scgi_request(stream, &body).await?
+            }
+            #[cfg(unix)]
+            Transport::ScgiUnix(path) => {
+                let stream = tokio::net::UnixStream::connect(path).await?;
+                scgi_request(stream, &body).await?
+            }
+        };
+
+        Ok(xmlrpc::decode_response(&response)?)
+    }
+
+    /// List all torrents in the `main` view.
+    async fn list_torrents(&self) -> Result<Vec<RTorrentTorrent>, RTorrentSeedboxError> {
+        let tag_getter = format!("d.{}=", self.tag_field);
+        let marker_getter = format!("d.custom={UPLOAD_MARKER_KEY}");
+        let params = [
+            "",
+            "main",
+            "d.hash=",
+            "d.name=",
+            "d.complete=",
+            "d.base_path=",
+            "d.directory=",
+            "d.is_multi_file=",
+            "d.load_date=",
+            tag_getter.as_str(),
+            marker_getter.as_str(),
+        ]
+        .map(Value::from);
+
+        let response = self.call("d.multicall2", &params).await?;
+        let rows = response.as_array().ok_or_else(|| {
+            RTorrentSeedboxError::InvalidResponse("d.multicall2 returned no array".to_string())
+        })?;
+
+        rows.iter()
+            .map(|row| {
+                row.as_array()
+                    .ok_or_else(|| {
+                        RTorrentSeedboxError::InvalidResponse(
+                            "d.multicall2 row is not an array".to_string(),
+                        )
+                    })
+                    .and_then(RTorrentTorrent::from_row)
+            })
+            .collect()
+    }
+
+    async fn lookup_uploaded_torrent(
+        &self,
+        marker: &str,
+    ) -> Result<RTorrentTorrent, RTorrentSeedboxError> {
+        for _ in 0..UPLOAD_LOOKUP_ATTEMPTS {
+            if let Some(torrent) = self
+                .list_torrents()
+                .await?
+                .into_iter()
+                .find(|t| t.upload_marker == marker)
+            {
+                let _ = self
+                    .call(
+                        "d.custom.set",
+                        &[
+                            Value::from(torrent.hash.as_str()),
+                            Value::from(UPLOAD_MARKER_KEY),
+                            Value::from(""),
+                        ],
+                    )
+                    .await
+                    .inspect_err(|e| {
+                        tracing::warn!(error = ?e, hash = %torrent.hash, "Failed to clear upload marker");
+                    });
+                return Ok(torrent);
+            }
+
+            tokio::time::sleep(UPLOAD_LOOKUP_INTERVAL).await;
+        }
+
+        Err(RTorrentSeedboxError::InvalidResponse(format!(
+            "Uploaded torrent with marker {marker} not found"
+        )))
+    }
+}
+
+/// Send an XML-RPC request over SCGI and return the response body.
+async fn scgi_request(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    body: &str,
+) -> Result<String, RTorrentSeedboxError> {
+    let headers = format!(
+        "CONTENT_LENGTH\0{}\0SCGI\01\0REQUEST_METHOD\0POST\0REQUEST_URI\0/RPC2\0",
+        body.len()
+    );
+    let request = format!("{}:{headers},{body}", headers.len());
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    // rTorrent closes the connection after the response.
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+
+    let body = response
+        .split_once("\r\n\r\n")
+        .or_else(|| response.split_once("\n\n"))
+        .map(|(_, body)| body)
+        .ok_or_else(|| {
+            RTorrentSeedboxError::InvalidResponse("Missing SCGI response headers".to_string())
+        })?;
+
+    Ok(body.to_string())
+}
+
+impl RTorrentTorrent {
+    fn from_row(row: &[Value]) -> Result<Self, RTorrentSeedboxError> {
+        let string = |idx: usize, field: &str| {
+            row.get(idx)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    RTorrentSeedboxError::InvalidResponse(format!(
+                        "Missing field {field} in torrent info"
+                    ))
+                })
+        };
+        let int = |idx: usize, field: &str| {
+            row.get(idx).and_then(Value::as_i64).ok_or_else(|| {
+                RTorrentSeedboxError::InvalidResponse(format!(
+                    "Missing field {field} in torrent info"
+                ))
+            })
+        };
+
+        Ok(Self {
+            // rTorrent reports uppercase hashes, but accepts either case.
+            hash: string(0, "d.hash")?.to_lowercase(),
+            name: string(1, "d.name")?,
+            complete: int(2, "d.complete")? != 0,
+            base_path: string(3, "d.base_path")?,
+            directory: string(4, "d.directory")?,
+            is_multi_file: int(5, "d.is_multi_file")? != 0,
+            load_date: int(6, "d.load_date")?,
+            tag: string(7, "tag field")?,
+            upload_marker: string(8, "d.custom")?,
+        })
+    }
+
+    fn into_task_info(self, tag: String) -> TorrentTaskInfo {
+        let status = if self.complete {
+            TorrentStatus::Seeding
+        } else {
+            TorrentStatus::Downloading
+        };
+        // `d.base_path` is empty while the torrent is closed.
+        let content_path = if !self.base_path.is_empty() {
+            self.base_path
+        } else if self.is_multi_file {
+            self.directory
+        } else {
+            format!("{}/{}", self.directory.trim_end_matches('/'), self.name)
+        };
+
+        TorrentTaskInfo {
+            hash: self.hash,
+            status,
+            content_path,
+            name: self.name,
+            tag,
+            // rTorrent has no categories, the custom field is used for tags.
+            category: None,
+            // `d.directory` is the content itself for multi-file torrents.
+            save_path: None,
+            stats: TorrentStats {
+                progress: if self.complete { 1.0 } else { 0.0 },
+                ..TorrentStats::default()
+            },
+            seedbox: String::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Seedbox for RTorrentSeedbox {
+    async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: &str,
+    ) -> anyhow::Result<TorrentTaskInfo> {
+        let sources = match source {
+            // `Sep` renders as newline separated list.
+            TorrentSource::Urls { urls } => urls
+                .to_string()
+                .lines()
+                .map(|url| ("load.start", Value::from(url)))
+                .collect::<Vec<_>>(),
+            TorrentSource::TorrentFiles { torrents } => torrents
+                .iter()
+                .map(|t| ("load.raw_start", Value::Base64(t.data.clone())))
+                .collect(),
+        };
+
+        let mut uploaded = None;
+        for (method, source) in sources {
+            let marker = format!(
+                "{}-{}",
+                Utc::now().timestamp(),
+                self.upload_counter.fetch_add(1, Ordering::Relaxed)
+            );
+            let params = [
+                Value::from(""),
+                source,
+                Value::String(format!("d.{}.set={tag}", self.tag_field)),
+                Value::String(format!("d.custom.set={UPLOAD_MARKER_KEY},{marker}")),
+            ];
+            self.call(method, &params).await?;
+
+            let info = self
+                .lookup_uploaded_torrent(&marker)
+                .await?
+                .into_task_info(tag.to_string());
+            tracing::info!(torrent = %info.name, hash = %info.hash, "Uploaded torrent");
+            uploaded.get_or_insert(info);
+        }
+
+        uploaded.ok_or_else(|| anyhow::anyhow!("No torrent given for upload"))
+    }
+
+    async fn query_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<TorrentTaskInfo>> {
+        tracing::debug!("Querying rTorrent XML-RPC for torrents");
+
+        let mut ignored_count = 0;
+        let mut tracked_count = 0;
+
+        let torrents = self
+            .list_torrents()
+            .await?
+            .into_iter()
+            .filter_map(|t| matching_tag(&self.tags, &[t.tag.as_str()]).map(|tag| (t, tag)))
+            .filter_map(|(t, tag)| {
+                if t.load_date < earliest_import_date.timestamp() {
+                    ignored_count += 1;
+                    tracing::trace!(torrent = %t.name, hash = %t.hash, "Ignoring torrent");
+                    return None;
+                }
+
+                tracked_count += 1;
+                tracing::trace!(torrent = %t.name, hash = %t.hash, "Tracking torrent");
+
+                Some(t.into_task_info(tag))
+            })
+            .collect::<Vec<_>>();
+
+        tracing::debug!(
+            tracked = tracked_count,
+            ignored = ignored_count,
+            "Queried torrents from XML-RPC"
+        );
+
+        Ok(torrents)
+    }
+
+    async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>> {
+        let torrents = self
+            .list_torrents()
+            .await?
+            .into_iter()
+            .filter_map(|t| matching_tag(&self.tags, &[t.tag.as_str()]).map(|tag| (t, tag)))
+            .map(|(t, tag)| {
+                let added = DateTime::from_timestamp(t.load_date, 0).unwrap_or_default();
+                (t.into_task_info(tag), added)
+            })
+            .collect();
+
+        Ok(torrents)
+    }
+
+    async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>> {
+        let response = self.call("download_list", &[Value::from("")]).await?;
+        let hashes = response
+            .as_array()
+            .ok_or_else(|| {
+                RTorrentSeedboxError::InvalidResponse("download_list returned no array".to_string())
+            })?
+            .iter()
+            .map(|hash| {
+                hash.as_str().map(str::to_lowercase).ok_or_else(|| {
+                    RTorrentSeedboxError::InvalidResponse(
+                        "download_list returned a non-string hash".to_string(),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageManager;
+
+    fn row(values: &[&str]) -> String {
+        let values = values
+            .iter()
+            .map(|v| match v.parse::<i64>() {
+                Ok(i) => format!("<value><i8>{i}</i8></value>"),
+                Err(_) => format!("<value><string>{v}</string></value>"),
+            })
+            .collect::<String>();
+        format!("<value><array><data>{values}</data></array></value>")
+    }
+
+    /// Serve a single SCGI request with a canned multicall response.
+    async fn spawn_mock_scgi() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let rows = [
+            row(&[
+                "AAAA",
+                "Show - 01.mkv",
+                "0",
+                "",
+                "/downloads",
+                "0",
+                "2000000000",
+                "anipler",
+                "",
+            ]),
+            row(&[
+                "BBBB",
+                "Show Batch",
+                "1",
+                "/downloads/Show Batch",
+                "/downloads/Show Batch",
+                "1",
+                "2000000000",
+                "anipler",
+                "",
+            ]),
+            row(&[
+                "CCCC",
+                "Unrelated",
+                "1",
+                "/downloads/Unrelated",
+                "/downloads",
+                "0",
+                "2000000000",
+                "",
+                "",
+            ]),
+            row(&[
+                "DDDD",
+                "Old",
+                "1",
+                "/downloads/Old",
+                "/downloads",
+                "0",
+                "0",
+                "anipler",
+                "",
+            ]),
+        ]
+        .concat();
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><methodResponse><params><param>\
+<value><array><data>{rows}</data></array></value></param></params></methodResponse>"
+        );
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).contains("</methodCall>") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            assert!(String::from_utf8_lossy(&request).contains("d.multicall2"));
+
+            let response = format!("Status: 200 OK\r\nContent-Type: text/xml\r\n\r\n{body}");
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        Url::parse(&format!("scgi://{addr}")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn query_torrents_filters_by_tag_field() {
+        let seedbox = RTorrentSeedbox::from_config(
+            &RTorrentConfig {
+                url: spawn_mock_scgi().await,
+                tag_field: "custom1".to_string(),
+                username: None,
+                password: None,
+            },
+            vec!["anipler".to_string()],
+        )
+        .unwrap();
+
+        let torrents = seedbox
+            .query_torrents(DateTime::from_timestamp(1_000_000_000, 0).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(torrents.len(), 2);
+        assert_eq!(torrents[0].hash, "aaaa");
+        assert!(matches!(torrents[0].status, TorrentStatus::Downloading));
+        assert_eq!(torrents[0].content_path, "/downloads/Show - 01.mkv");
+        assert_eq!(torrents[1].hash, "bbbb");
+        assert!(matches!(torrents[1].status, TorrentStatus::Seeding));
+        assert_eq!(torrents[1].content_path, "/downloads/Show Batch");
+        assert_eq!(torrents[1].tag, "anipler");
+    }
+
+    #[tokio::test]
+    async fn hash_prefix_commands_match_rtorrent_tasks() {
+        let seedbox = RTorrentSeedbox::from_config(
+            &RTorrentConfig {
+                url: spawn_mock_scgi().await,
+                tag_field: "custom1".to_string(),
+                username: None,
+                password: None,
+            },
+            vec!["anipler".to_string()],
+        )
+        .unwrap();
+        let store = StorageManager::in_memory().await;
+
+        let torrents = seedbox
+            .query_torrents(DateTime::from_timestamp(1_000_000_000, 0).unwrap())
+            .await
+            .unwrap();
+        store.update_torrent_info(&torrents).await.unwrap();
+        store
+            .record_transfer_failure("bbbb", "permission denied", |_| None)
+            .await
+            .unwrap();
+
+        // Prefixes given by the user are matched in lowercase.
+        let retried = store.retry_failed_tasks(Some("BB")).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].hash, "bbbb");
+    }
+}
@@ -0,0 +1,346 @@
+//! Seedbox backend abstraction.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use qbit_rs::model::TorrentSource;
+use serde::Deserialize;
+
+use crate::{
+    config::{DaemonConfig, RssRule, SeedboxConfig},
+    deluge::DelugeSeedbox,
+    qbit::QBitSeedbox,
+    rtorrent::RTorrentSeedbox,
+    task::TorrentTaskInfo,
+    transmission::TransmissionSeedbox,
+};
+
+/// Torrent client running on the seedbox.
+///
+/// Implementations only report torrents carrying one of the tags (or labels,
+/// depending on the backend) configured in `[[tags]]`, and are expected to be
+/// cheap to share between the daemon and the API server.
+#[async_trait]
+pub trait Seedbox: Send + Sync {
+    /// Add a torrent to the seedbox with the given tracked tag applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the torrent client rejects the torrent, or it can not
+    /// be found on the seedbox afterwards.
+    async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: &str,
+    ) -> anyhow::Result<TorrentTaskInfo>;
+
+    /// Query all torrents that should managed by the program from the seedbox.
+    ///
+    /// Torrents added before `earliest_import_date` are ignored. Backends
+    /// supporting incremental polling may only report torrents changed since
+    /// the previous query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the response is malformed.
+    async fn query_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<TorrentTaskInfo>>;
+
+    /// Forget incremental polling state, so that the next
+    /// [`Seedbox::query_torrents`] reports every torrent again.
+    ///
+    /// Called when the results of a query could not be stored, as they would
+    /// not be reported again otherwise.
+    async fn reset_sync(&self) {}
+
+    /// List all torrents carrying a tracked tag along with the time they were
+    /// added, regardless of the earliest import date.
+    ///
+    /// Unlike [`Seedbox::query_torrents`], this always reports every torrent.
+    /// Used to adopt torrents added before the earliest import date.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the response is malformed.
+    async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>>;
+
+    /// List torrents without a tracked tag added since `earliest_import_date`,
+    /// to be matched by auto-tagging rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn untracked_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<UntrackedTorrent>> {
+        let _ = earliest_import_date;
+        anyhow::bail!("Listing untracked torrents is not supported by this seedbox backend")
+    }
+
+    /// Hashes of all torrents on the seedbox, tracked or not.
+    ///
+    /// Used to detect torrents deleted from the seedbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the response is malformed.
+    async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>>;
+
+    /// List files of a torrent selected for download, relative to the parent
+    /// directory of its `content_path`.
+    ///
+    /// Returns `None` if the backend can not list files, in which case the
+    /// torrent is transferred as a whole.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the response is malformed.
+    async fn torrent_files(&self, hash: &str) -> anyhow::Result<Option<Vec<String>>> {
+        let _ = hash;
+        Ok(None)
+    }
+
+    /// Free space on the download disk of the seedbox in bytes.
+    ///
+    /// Returns `None` if the backend does not report it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the response is malformed.
+    async fn free_space(&self) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Pause the given torrents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn pause_torrents(&self, hashes: &[String]) -> anyhow::Result<()> {
+        let _ = hashes;
+        anyhow::bail!("Pausing torrents is not supported by this seedbox backend")
+    }
+
+    /// Resume the given torrents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn resume_torrents(&self, hashes: &[String]) -> anyhow::Result<()> {
+        let _ = hashes;
+        anyhow::bail!("Resuming torrents is not supported by this seedbox backend")
+    }
+
+    /// Create or replace an RSS auto-download rule, applying `tag` and the
+    /// category of the rule to downloaded torrents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn set_rss_rule(&self, rule: &RssRule, tag: &str) -> anyhow::Result<()> {
+        let _ = (rule, tag);
+        anyhow::bail!("RSS rules are not supported by this seedbox backend")
+    }
+
+    /// Remove an RSS auto-download rule.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn remove_rss_rule(&self, name: &str) -> anyhow::Result<()> {
+        let _ = name;
+        anyhow::bail!("RSS rules are not supported by this seedbox backend")
+    }
+
+    /// Add a tag to a torrent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn add_tag(&self, hash: &str, tag: &str) -> anyhow::Result<()> {
+        let _ = (hash, tag);
+        anyhow::bail!("Adding tags is not supported by this seedbox backend")
+    }
+
+    /// Remove a tag from a torrent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn remove_tag(&self, hash: &str, tag: &str) -> anyhow::Result<()> {
+        let _ = (hash, tag);
+        anyhow::bail!("Removing tags is not supported by this seedbox backend")
+    }
+
+    /// Limit seeding of a torrent by share ratio and/or seeding time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn set_share_limits(
+        &self,
+        hash: &str,
+        ratio: Option<f64>,
+        seeding_time: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        let _ = (hash, ratio, seeding_time);
+        anyhow::bail!("Share limits are not supported by this seedbox backend")
+    }
+
+    /// Current share ratio and seeding time of a torrent, `None` if it is gone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn share_stats(&self, hash: &str) -> anyhow::Result<Option<ShareStats>> {
+        let _ = hash;
+        anyhow::bail!("Share statistics are not supported by this seedbox backend")
+    }
+
+    /// Delete a torrent, optionally along with its data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API request fails or the backend does not support it.
+    async fn delete_torrent(&self, hash: &str, delete_data: bool) -> anyhow::Result<()> {
+        let _ = (hash, delete_data);
+        anyhow::bail!("Deleting torrents is not supported by this seedbox backend")
+    }
+}
+
+/// Torrent without a tracked tag, as matched by auto-tagging rules.
+#[derive(Debug, Clone)]
+pub struct UntrackedTorrent {
+    pub hash: String,
+    pub name: String,
+    pub category: Option<String>,
+    /// URL of the current tracker.
+    pub tracker: Option<String>,
+    pub save_path: Option<String>,
+}
+
+/// Seeding statistics of a torrent.
+#[derive(Debug, Clone, Copy)]
+pub struct ShareStats {
+    pub ratio: f64,
+    pub seeding_time: Duration,
+}
+
+/// Torrent client implementation selected by `seedbox.backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedboxBackend {
+    #[default]
+    QBit,
+    Transmission,
+    Deluge,
+    RTorrent,
+}
+
+/// Seedboxes managed by the daemon, in configuration order.
+#[derive(Clone)]
+pub struct SeedboxSet {
+    seedboxes: Vec<(String, Arc<dyn Seedbox>)>,
+}
+
+impl SeedboxSet {
+    /// Create the clients of all seedboxes in the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration section of a selected backend is
+    /// missing, or a client can not be created.
+    pub fn from_config(config: &DaemonConfig) -> anyhow::Result<Self> {
+        let tags = config.tag_names();
+        let seedboxes = config
+            .seedboxes
+            .iter()
+            .map(|seedbox| Ok((seedbox.name.clone(), from_config(seedbox, tags.clone())?)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { seedboxes })
+    }
+
+    /// Set of already created clients, e.g. mocks in tests.
+    #[cfg(test)]
+    pub(crate) fn from_clients(seedboxes: Vec<(String, Arc<dyn Seedbox>)>) -> Self {
+        Self { seedboxes }
+    }
+
+    /// Client of a named seedbox.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Seedbox>> {
+        self.seedboxes
+            .iter()
+            .find(|(seedbox, _)| seedbox == name)
+            .map(|(_, client)| client)
+    }
+
+    /// Name and client of the seedbox receiving uploads by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set is empty, which configuration validation rules out.
+    pub fn primary(&self) -> (&str, &Arc<dyn Seedbox>) {
+        let (name, client) = self.seedboxes.first().expect("no seedbox configured");
+        (name, client)
+    }
+
+    /// Iterate over names and clients of all seedboxes.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<dyn Seedbox>)> {
+        self.seedboxes
+            .iter()
+            .map(|(name, client)| (name.as_str(), client))
+    }
+}
+
+/// Create the client of a single seedbox.
+///
+/// # Errors
+///
+/// Returns an error if the configuration section of the selected backend is missing.
+fn from_config(config: &SeedboxConfig, tags: Vec<String>) -> anyhow::Result<Arc<dyn Seedbox>> {
+    let missing = |section: &str| {
+        anyhow::anyhow!(
+            "Missing [{section}] configuration for seedbox {}",
+            config.name
+        )
+    };
+    let seedbox: Arc<dyn Seedbox> = match config.backend {
+        SeedboxBackend::QBit => {
+            let qbit = config.qbit.as_ref().ok_or_else(|| missing("qbit"))?;
+            Arc::new(QBitSeedbox::from_config(qbit, tags)?)
+        }
+        SeedboxBackend::Transmission => {
+            let transmission = config
+                .transmission
+                .as_ref()
+                .ok_or_else(|| missing("transmission"))?;
+            Arc::new(TransmissionSeedbox::from_config(transmission, tags))
+        }
+        SeedboxBackend::Deluge => {
+            let deluge = config.deluge.as_ref().ok_or_else(|| missing("deluge"))?;
+            Arc::new(DelugeSeedbox::from_config(deluge, tags)?)
+        }
+        SeedboxBackend::RTorrent => {
+            let rtorrent = config
+                .rtorrent
+                .as_ref()
+                .ok_or_else(|| missing("rtorrent"))?;
+            Arc::new(RTorrentSeedbox::from_config(rtorrent, tags)?)
+        }
+    };
+
+    Ok(seedbox)
+}
+
+/// Find the tracked tag with the highest precedence among the tags of a torrent.
+pub fn matching_tag(tracked: &[String], tags: &[&str]) -> Option<String> {
+    tracked
+        .iter()
+        .find(|tracked| tags.contains(&tracked.as_str()))
+        .cloned()
+}
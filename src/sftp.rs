@@ -0,0 +1,741 @@
+//! Native SFTP transfer execution, an alternative to spawning rsync.
+
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use russh::client::{self, Handle};
+use russh::keys::{PrivateKeyWithHashAlg, PublicKey};
+use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::StatusCode;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::config::DaemonConfig;
+use crate::task::TransferTaskInfo;
+
+const DEFAULT_SSH_PORT: u16 = 22;
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Downloads torrent content over SFTP without external binaries.
+///
+/// The artifact layout matches rsync: the content is placed under the
+/// destination directory by its own name, or the manifest files by their paths
+/// relative to the save path of the torrent.
+///
+/// Files are downloaded to a hidden `.partial` file next to their destination
+/// and renamed when complete, so an interrupted transfer resumes from the
+/// partial file as long as the remote file is unchanged. Completed files keep the remote modification time and are
+/// skipped while size and modification time are unchanged. Like rsync
+/// `--delete`, full transfers remove local files missing on the seedbox.
+pub struct SftpExecutor {
+    /// SSH connection of each seedbox by name.
+    hosts: HashMap<String, SshTarget>,
+    speed_limit: Option<u32>,
+    dry_run: bool,
+}
+
+struct SshTarget {
+    /// `[user@]host[:port]` as configured in `ssh_host`.
+    host: String,
+    key_path: PathBuf,
+}
+
+/// Parsed `[user@]host[:port]` SSH target.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SshAddress {
+    pub user: String,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Accepts any host key, like `StrictHostKeyChecking=no` for rsync.
+struct AcceptHostKey;
+
+impl client::Handler for AcceptHostKey {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Sleeps as needed to keep the average rate below a bandwidth limit.
+struct RateLimiter {
+    bytes_per_sec: Option<u64>,
+    started: Instant,
+    transferred: u64,
+}
+
+impl SftpExecutor {
+    /// Build SFTP executor from daemon configuration.
+    pub fn from_config(config: &DaemonConfig) -> Self {
+        let hosts = config
+            .seedboxes
+            .iter()
+            .map(|seedbox| {
+                tracing::debug!(seedbox = %seedbox.name, host = %seedbox.ssh_host, "Adding SFTP source host");
+                let target = SshTarget {
+                    host: seedbox.ssh_host.clone(),
+                    key_path: seedbox.ssh_key.clone(),
+                };
+                (seedbox.name.clone(), target)
+            })
+            .collect();
+
+        Self {
+            hosts,
+            speed_limit: config.transfer.speed_limit,
+            dry_run: config.transfer.is_dry_run(),
+        }
+    }
+
+    /// Execute a single transfer task over SFTP.
+    ///
+    /// With a `manifest`, only the listed files are transferred. They are
+    /// relative to the save path of the torrent, see
+    /// [`TransferTaskInfo::manifest_root`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection, authentication, a remote operation
+    /// or a local file operation fails.
+    pub async fn transfer(
+        &self,
+        task: &TransferTaskInfo,
+        manifest: Option<&[String]>,
+    ) -> Result<(), SftpTransferError> {
+        let target =
+            self.hosts
+                .get(&task.seedbox)
+                .ok_or_else(|| SftpTransferError::UnknownSeedbox {
+                    seedbox: task.seedbox.clone(),
+                })?;
+        let source = task.source.trim_end_matches('/');
+        let (parent, name) =
+            source
+                .rsplit_once('/')
+                .ok_or_else(|| SftpTransferError::NoParentDirectory {
+                    source_path: task.source.clone(),
+                })?;
+        relative_path(name)?;
+        let root = match manifest {
+            Some(_) => task.manifest_root().unwrap_or(parent),
+            None => parent,
+        };
+        let dest = Path::new(&task.dest);
+
+        tracing::info!(seedbox = %task.seedbox, source = %source, dest = %task.dest, "Transferring files over SFTP");
+        if self.dry_run {
+            tracing::info!(source = %source, dest = %task.dest, "Skipping transfer in dry_run mode");
+            return Ok(());
+        }
+
+        let address = parse_ssh_host(&target.host)?;
+        let sftp = connect(&address, &target.key_path).await?;
+
+        let files = match manifest {
+            Some(files) => {
+                tracing::debug!(count = files.len(), "Transferring selected files");
+                files.to_vec()
+            }
+            None => list_remote_files(&sftp, root, name).await?,
+        };
+
+        let mut limiter = RateLimiter::new(task.speed_limit.or(self.speed_limit));
+        for file in &files {
+            let local = dest.join(relative_path(file)?);
+            download(&sftp, &format!("{root}/{file}"), &local, &mut limiter).await?;
+        }
+
+        if manifest.is_none() {
+            let keep = files.iter().map(|file| dest.join(file)).collect();
+            remove_extraneous(&dest.join(name), &keep).await?;
+        }
+
+        let _ = sftp.close().await;
+
+        tracing::info!(dest = %task.dest, count = files.len(), "Artifact available");
+
+        Ok(())
+    }
+}
+
+/// Parse an `ssh_host` value of the form `[user@]host[:port]`.
+///
+/// Without a user, the user running the daemon is assumed. Unlike `ssh`,
+/// aliases from `~/.ssh/config` are not resolved.
+///
+/// # Errors
+///
+/// Returns an error if the port is invalid, or no user is given and `$USER`
+/// is not set.
+pub fn parse_ssh_host(target: &str) -> Result<SshAddress, SftpTransferError> {
+    let invalid = |reason: &str| SftpTransferError::InvalidHost {
+        host: target.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let (user, host) = match target.split_once('@') {
+        Some((user, host)) => (user.to_string(), host),
+        None => (
+            std::env::var("USER").map_err(|_| invalid("no user given and $USER is not set"))?,
+            target,
+        ),
+    };
+    let (host, port) = match host.rsplit_once(':') {
+        // Bare IPv6 addresses contain colons but no port.
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            let port = port.parse().map_err(|_| invalid("invalid port"))?;
+            (host, port)
+        }
+        _ => (host, DEFAULT_SSH_PORT),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if user.is_empty() || host.is_empty() {
+        return Err(invalid("empty user or host"));
+    }
+
+    Ok(SshAddress {
+        user,
+        host: host.to_string(),
+        port,
+    })
+}
+
+/// Open an SFTP session authenticated with a private key.
+async fn connect(address: &SshAddress, key_path: &Path) -> Result<SftpSession, SftpTransferError> {
+    let host = format!("{}:{}", address.host, address.port);
+    let key = russh::keys::load_secret_key(key_path, None).map_err(|source| {
+        SftpTransferError::LoadKey {
+            path: key_path.to_path_buf(),
+            source,
+        }
+    })?;
+
+    let connect_error = |source| SftpTransferError::Connect {
+        host: host.clone(),
+        source,
+    };
+    let config = Arc::new(client::Config::default());
+    let mut session: Handle<AcceptHostKey> =
+        client::connect(config, (address.host.as_str(), address.port), AcceptHostKey)
+            .await
+            .map_err(connect_error)?;
+
+    let hash_alg = session
+        .best_supported_rsa_hash()
+        .await
+        .map_err(connect_error)?
+        .flatten();
+    let auth = session
+        .authenticate_publickey(
+            &address.user,
+            PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg),
+        )
+        .await
+        .map_err(connect_error)?;
+    if !auth.success() {
+        return Err(SftpTransferError::AuthenticationRejected {
+            user: address.user.clone(),
+            host: host.clone(),
+        });
+    }
+
+    let channel = session
+        .channel_open_session()
+        .await
+        .map_err(connect_error)?;
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .map_err(connect_error)?;
+
+    SftpSession::new(channel.into_stream())
+        .await
+        .map_err(|source| SftpTransferError::Sftp {
+            path: String::new(),
+            source,
+        })
+}
+
+/// List regular files of the content `name` in `root`, relative to `root`.
+async fn list_remote_files(
+    sftp: &SftpSession,
+    root: &str,
+    name: &str,
+) -> Result<Vec<String>, SftpTransferError> {
+    let sftp_error = |path: &str| {
+        let path = path.to_string();
+        move |source| SftpTransferError::Sftp { path, source }
+    };
+
+    let path = format!("{root}/{name}");
+    let metadata = sftp.metadata(&path).await.map_err(sftp_error(&path))?;
+    if !metadata.is_dir() {
+        return Ok(vec![name.to_string()]);
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![name.to_string()];
+    while let Some(dir) = pending.pop() {
+        let path = format!("{root}/{dir}");
+        for entry in sftp.read_dir(&path).await.map_err(sftp_error(&path))? {
+            let file_name = entry.file_name();
+            if file_name == "." || file_name == ".." {
+                continue;
+            }
+
+            let relative = format!("{dir}/{file_name}");
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                pending.push(relative);
+            } else if file_type.is_file() {
+                files.push(relative);
+            } else {
+                // rsync without `--links` skips symlinks and special files as well.
+                tracing::debug!(path = %relative, "Skipping non-regular file");
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Check that a file of the content is a plain relative path, so that it can
+/// not be written outside of the destination directory.
+fn relative_path(file: &str) -> Result<&Path, SftpTransferError> {
+    let path = Path::new(file);
+    let mut components = path.components().peekable();
+    if components.peek().is_some() && components.all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(SftpTransferError::UnsafePath {
+            path: file.to_string(),
+        })
+    }
+}
+
+/// Download one file, resuming from its partial file if present and the
+/// remote file did not change since it was started.
+async fn download(
+    sftp: &SftpSession,
+    remote: &str,
+    local: &Path,
+    limiter: &mut RateLimiter,
+) -> Result<(), SftpTransferError> {
+    let sftp_error = |source| SftpTransferError::Sftp {
+        path: remote.to_string(),
+        source,
+    };
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| SftpTransferError::Io { path, source }
+    };
+
+    let metadata = sftp.metadata(remote).await.map_err(sftp_error)?;
+    let size = metadata.size.unwrap_or_default();
+    let modified = metadata
+        .mtime
+        .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime.into()));
+
+    if let Ok(existing) = fs::metadata(local).await
+        && existing.len() == size
+        && modified.is_some()
+        && existing.modified().ok() == modified
+    {
+        tracing::trace!(path = %local.display(), "Skipping unchanged file");
+        return Ok(());
+    }
+
+    let partial = partial_path(local);
+    let stamp_path = partial_stamp_path(local);
+    // Size and modification time of the remote file the partial file belongs to.
+    let stamp = metadata.mtime.map(|mtime| format!("{size} {mtime}"));
+    let offset = match (
+        fs::metadata(&partial).await,
+        fs::read_to_string(&stamp_path).await,
+    ) {
+        (Ok(existing), Ok(recorded))
+            if existing.len() <= size && stamp.as_deref() == Some(recorded.as_str()) =>
+        {
+            existing.len()
+        }
+        _ => 0,
+    };
+    if let Some(parent) = local.parent() {
+        fs::create_dir_all(parent).await.map_err(io_error(parent))?;
+    }
+    if offset == 0 {
+        match &stamp {
+            Some(stamp) => fs::write(&stamp_path, stamp)
+                .await
+                .map_err(io_error(&stamp_path))?,
+            None => remove_if_exists(&stamp_path).await?,
+        }
+    }
+
+    tracing::debug!(remote = %remote, size = size, offset = offset, "Downloading file");
+    let mut output = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(offset == 0)
+        .open(&partial)
+        .await
+        .map_err(io_error(&partial))?;
+    output
+        .seek(SeekFrom::Start(offset))
+        .await
+        .map_err(io_error(&partial))?;
+
+    let mut input = sftp.open(remote).await.map_err(sftp_error)?;
+    input
+        .seek(SeekFrom::Start(offset))
+        .await
+        .map_err(io_error(Path::new(remote)))?;
+
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let read = input
+            .read(&mut buf)
+            .await
+            .map_err(io_error(Path::new(remote)))?;
+        if read == 0 {
+            break;
+        }
+        output
+            .write_all(&buf[..read])
+            .await
+            .map_err(io_error(&partial))?;
+        limiter.consume(read).await;
+    }
+    output.flush().await.map_err(io_error(&partial))?;
+
+    let output = output.into_std().await;
+    if let Some(modified) = modified {
+        output.set_modified(modified).map_err(io_error(&partial))?;
+    }
+    drop(output);
+    fs::rename(&partial, local).await.map_err(io_error(local))?;
+    remove_if_exists(&stamp_path).await?;
+
+    Ok(())
+}
+
+async fn remove_if_exists(path: &Path) -> Result<(), SftpTransferError> {
+    match fs::remove_file(path).await {
+        Err(source) if source.kind() != ErrorKind::NotFound => Err(SftpTransferError::Io {
+            path: path.to_path_buf(),
+            source,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Hidden file a download is written to until it is complete.
+fn partial_path(local: &Path) -> PathBuf {
+    let name = local
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    local.with_file_name(format!(".{name}.partial"))
+}
+
+/// Hidden file recording which version of the remote file the partial file
+/// holds the beginning of.
+fn partial_stamp_path(local: &Path) -> PathBuf {
+    let mut path = partial_path(local).into_os_string();
+    path.push(".stamp");
+    PathBuf::from(path)
+}
+
+/// Remove files and directories under `root` that are not in `keep`, nor a
+/// parent directory of a kept file.
+async fn remove_extraneous(root: &Path, keep: &HashSet<PathBuf>) -> Result<(), SftpTransferError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| SftpTransferError::Io { path, source }
+    };
+
+    if !fs::metadata(root).await.is_ok_and(|m| m.is_dir()) {
+        return Ok(());
+    }
+
+    let kept_dirs = keep
+        .iter()
+        .flat_map(|path| path.ancestors().skip(1))
+        .collect::<HashSet<_>>();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await.map_err(io_error(&dir))?;
+        while let Some(entry) = entries.next_entry().await.map_err(io_error(&dir))? {
+            let path = entry.path();
+            let is_dir = entry.file_type().await.map_err(io_error(&path))?.is_dir();
+            if is_dir && kept_dirs.contains(path.as_path()) {
+                pending.push(path);
+            } else if is_dir {
+                tracing::debug!(path = %path.display(), "Removing extraneous directory");
+                fs::remove_dir_all(&path).await.map_err(io_error(&path))?;
+            } else if !keep.contains(&path) {
+                tracing::debug!(path = %path.display(), "Removing extraneous file");
+                fs::remove_file(&path).await.map_err(io_error(&path))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl RateLimiter {
+    /// Create a limiter for a bandwidth limit in KiB/s, unlimited if `None`.
+    fn new(limit: Option<u32>) -> Self {
+        Self {
+            bytes_per_sec: limit.map(|limit| u64::from(limit) * 1024),
+            started: Instant::now(),
+            transferred: 0,
+        }
+    }
+
+    /// Account for `bytes` transferred, sleeping if ahead of the limit.
+    async fn consume(&mut self, bytes: usize) {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return;
+        };
+
+        self.transferred += bytes as u64;
+        let delay = throttle_delay(self.transferred, bytes_per_sec, self.started.elapsed());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Time to wait so that `transferred` bytes took at least as long as allowed
+/// by the rate, given the `elapsed` time.
+fn throttle_delay(transferred: u64, bytes_per_sec: u64, elapsed: Duration) -> Duration {
+    if bytes_per_sec == 0 {
+        return Duration::ZERO;
+    }
+    let expected_ms = u128::from(transferred) * 1000 / u128::from(bytes_per_sec);
+    let expected = Duration::from_millis(u64::try_from(expected_ms).unwrap_or(u64::MAX));
+    expected.saturating_sub(elapsed)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SftpTransferError {
+    #[error("No SSH host is configured for seedbox {seedbox}")]
+    UnknownSeedbox { seedbox: String },
+    #[error("Invalid SSH host {host}: {reason}")]
+    InvalidHost { host: String, reason: String },
+    #[error("Source {source_path} has no parent directory")]
+    NoParentDirectory { source_path: String },
+    #[error("Refusing to transfer {path:?} outside of the destination directory")]
+    UnsafePath { path: String },
+    #[error("Failed to load SSH key {}: {source}", path.display())]
+    LoadKey {
+        path: PathBuf,
+        source: russh::keys::Error,
+    },
+    #[error("SSH connection to {host} failed: {source}")]
+    Connect { host: String, source: russh::Error },
+    #[error("SSH authentication rejected for {user}@{host}")]
+    AuthenticationRejected { user: String, host: String },
+    #[error("SFTP operation failed on {path:?}: {source}")]
+    Sftp {
+        path: String,
+        source: russh_sftp::client::error::Error,
+    },
+    #[error("I/O error on {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl SftpTransferError {
+    /// Whether retrying the transfer later may succeed, e.g. after a network
+    /// failure, as opposed to configuration errors, rejected credentials,
+    /// missing permissions or missing remote files.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Connect { source, .. } => !matches!(
+                source,
+                russh::Error::NotAuthenticated | russh::Error::NoAuthMethod
+            ),
+            Self::Sftp {
+                source: russh_sftp::client::error::Error::Status(status),
+                ..
+            } => !matches!(
+                status.status_code,
+                StatusCode::NoSuchFile | StatusCode::PermissionDenied
+            ),
+            Self::Sftp { .. } => true,
+            Self::Io { source, .. } => !matches!(
+                source.kind(),
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem
+            ),
+            Self::UnknownSeedbox { .. }
+            | Self::InvalidHost { .. }
+            | Self::NoParentDirectory { .. }
+            | Self::UnsafePath { .. }
+            | Self::LoadKey { .. }
+            | Self::AuthenticationRejected { .. } => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_host_parses_user_and_port() {
+        assert_eq!(
+            parse_ssh_host("user@seedbox.example:2222").unwrap(),
+            SshAddress {
+                user: "user".to_string(),
+                host: "seedbox.example".to_string(),
+                port: 2222,
+            }
+        );
+        assert_eq!(parse_ssh_host("user@[::1]:22").unwrap().host, "::1");
+        assert_eq!(parse_ssh_host("user@::1").unwrap().port, DEFAULT_SSH_PORT);
+        assert!(parse_ssh_host("user@seedbox.example:ssh").is_err());
+        assert!(parse_ssh_host("@seedbox.example").is_err());
+    }
+
+    #[test]
+    fn throttle_delay_waits_for_bytes_ahead_of_rate() {
+        let rate = 1024 * 1024;
+
+        assert_eq!(
+            throttle_delay(2 * rate, rate, Duration::from_millis(500)),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            throttle_delay(rate, rate, Duration::from_secs(2)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn partial_file_is_hidden_next_to_destination() {
+        assert_eq!(
+            partial_path(Path::new("/relay/Show/Show - 01.mkv")),
+            Path::new("/relay/Show/.Show - 01.mkv.partial")
+        );
+        assert_eq!(
+            partial_stamp_path(Path::new("/relay/Show/Show - 01.mkv")),
+            Path::new("/relay/Show/.Show - 01.mkv.partial.stamp")
+        );
+    }
+
+    #[test]
+    fn paths_escaping_the_destination_are_rejected() {
+        assert!(relative_path("Show/Show - 01.mkv").is_ok());
+        assert!(relative_path("Show - 01.mkv").is_ok());
+        for path in ["", "..", "Show/../../etc/passwd", "/etc/passwd", "./Show"] {
+            assert!(
+                matches!(
+                    relative_path(path),
+                    Err(SftpTransferError::UnsafePath { .. })
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn permission_and_missing_file_errors_are_permanent() {
+        let status = |status_code| SftpTransferError::Sftp {
+            path: "/downloads/Show".to_string(),
+            source: russh_sftp::protocol::Status {
+                id: 0,
+                status_code,
+                error_message: String::new(),
+                language_tag: String::new(),
+            }
+            .into(),
+        };
+        let io = |kind: ErrorKind| SftpTransferError::Io {
+            path: PathBuf::from("/relay/Show"),
+            source: kind.into(),
+        };
+
+        assert!(status(StatusCode::Failure).is_transient());
+        assert!(status(StatusCode::ConnectionLost).is_transient());
+        assert!(!status(StatusCode::NoSuchFile).is_transient());
+        assert!(!status(StatusCode::PermissionDenied).is_transient());
+        assert!(io(ErrorKind::ConnectionReset).is_transient());
+        assert!(!io(ErrorKind::PermissionDenied).is_transient());
+        assert!(
+            !SftpTransferError::AuthenticationRejected {
+                user: "user".to_string(),
+                host: "seedbox.example:22".to_string(),
+            }
+            .is_transient()
+        );
+    }
+
+    /// Transfer from a real SSH server, e.g. a local OpenSSH with the key of
+    /// the current user authorized:
+    ///
+    /// `ANIPLER_SFTP_TEST_HOST=$USER@localhost ANIPLER_SFTP_TEST_KEY=~/.ssh/id_ed25519 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "requires an SSH server, see ANIPLER_SFTP_TEST_HOST"]
+    async fn transfer_resumes_partial_files_over_sftp() {
+        let host = std::env::var("ANIPLER_SFTP_TEST_HOST").unwrap();
+        let key = std::env::var("ANIPLER_SFTP_TEST_KEY").unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        let content = source.path().join("Show");
+        std::fs::create_dir_all(content.join("Extras")).unwrap();
+        std::fs::write(content.join("Show - 01.mkv"), b"0123456789").unwrap();
+        std::fs::write(content.join("Extras/NCOP.mkv"), b"ncop").unwrap();
+        // An interrupted earlier transfer, and a file removed on the seedbox.
+        std::fs::create_dir_all(dest.path().join("Show")).unwrap();
+        std::fs::write(dest.path().join("Show/.Show - 01.mkv.partial"), b"01234").unwrap();
+        std::fs::write(dest.path().join("Show/Show - 00.mkv"), b"old").unwrap();
+
+        let executor = SftpExecutor {
+            hosts: HashMap::from([(
+                "default".to_string(),
+                SshTarget {
+                    host,
+                    key_path: PathBuf::from(key),
+                },
+            )]),
+            speed_limit: None,
+            dry_run: false,
+        };
+        let task = TransferTaskInfo {
+            hash: "hash".to_string(),
+            source: content.to_string_lossy().to_string(),
+            dest: dest.path().to_string_lossy().to_string(),
+            name: "Show".to_string(),
+            tag: "anipler".to_string(),
+            category: None,
+            speed_limit: Some(1024),
+            seedbox: "default".to_string(),
+            save_path: Some(source.path().to_string_lossy().to_string()),
+        };
+        executor.transfer(&task, None).await.unwrap();
+
+        let show = dest.path().join("Show");
+        assert_eq!(
+            std::fs::read(show.join("Show - 01.mkv")).unwrap(),
+            b"0123456789"
+        );
+        assert_eq!(
+            std::fs::read(show.join("Extras/NCOP.mkv")).unwrap(),
+            b"ncop"
+        );
+        assert!(!show.join("Show - 00.mkv").exists());
+        assert!(!show.join(".Show - 01.mkv.partial").exists());
+    }
+}
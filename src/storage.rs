@@ -1,15 +1,51 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
 
 use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
 
 use crate::{
-    config::DaemonConfig,
-    task::{ArtifactInfo, TorrentStatus, TorrentTaskInfo},
+    config::{DaemonConfig, RssRule},
+    task::{
+        ArtifactInfo, FailedTask, ManagedRssRule, PendingCleanup, SeedboxSpace, TaskSummary,
+        TorrentStats, TorrentStatus, TorrentTaskInfo, TransferFailure, UnhealthyTask,
+    },
 };
 
 const EARLIEST_IMPORT_DATE_KEY: &str = "earliest_import_date";
 
+/// Columns added to `tasks` after its initial schema, applied on startup to
+/// databases created by older versions.
+const TASK_COLUMNS: &[(&str, &str)] = &[
+    ("tag", "TEXT NOT NULL DEFAULT 'anipler'"),
+    ("category", "TEXT"),
+    ("manifest", "TEXT"),
+    ("cleanup_pending", "INTEGER NOT NULL DEFAULT 0"),
+    ("size", "INTEGER"),
+    ("progress", "REAL NOT NULL DEFAULT 0"),
+    ("eta", "INTEGER"),
+    ("dlspeed", "INTEGER"),
+    ("num_seeds", "INTEGER"),
+    ("state", "TEXT"),
+    ("torrent_status", "INTEGER"),
+    ("unhealthy_since", "INTEGER"),
+    ("unhealthy_alerted", "INTEGER NOT NULL DEFAULT 0"),
+    ("seedbox", "TEXT NOT NULL DEFAULT 'default'"),
+    ("paused", "INTEGER NOT NULL DEFAULT 0"),
+    ("completion_on", "INTEGER"),
+    ("transferred_size", "INTEGER"),
+    ("transferred_completion_on", "INTEGER"),
+    ("content_changed", "INTEGER NOT NULL DEFAULT 0"),
+    ("transfer_attempts", "INTEGER NOT NULL DEFAULT 0"),
+    ("last_error", "TEXT"),
+    ("next_attempt_at", "INTEGER"),
+    ("transfer_held", "INTEGER NOT NULL DEFAULT 0"),
+    ("save_path", "TEXT"),
+    ("cleanup_applied", "INTEGER NOT NULL DEFAULT 0"),
+];
+
 #[derive(Debug, thiserror::Error)]
 pub enum FinalizeArtifactError {
     #[error("Artifact not found")]
@@ -32,11 +68,17 @@ pub enum TaskStatus {
     ArtifactReady = 2,
     /// Pull complete, preserve the record so that it won't be re-tracked.
     Archived = 3,
+    /// Torrent vanished from the seedbox before its artifact was ready.
+    Missing = 4,
+    /// Transfer failed too many times, waiting for a manual retry.
+    Failed = 5,
 }
 
 pub struct StorageManager {
     state: RwLock<StorageState>,
     storage_path: PathBuf,
+    /// Relay subdirectory of artifacts by tag.
+    relay_subdirs: HashMap<String, PathBuf>,
 }
 
 struct StorageState {
@@ -60,9 +102,16 @@ impl StorageManager {
 
         let state = RwLock::new(StorageState { db });
 
+        let relay_subdirs = config
+            .tags
+            .iter()
+            .filter_map(|tag| Some((tag.name.clone(), tag.relay_subdir.clone()?)))
+            .collect();
+
         let this = Self {
             state,
             storage_path,
+            relay_subdirs,
         };
 
         this.init().await?;
@@ -70,12 +119,32 @@ impl StorageManager {
         Ok(this)
     }
 
+    /// Create an empty in-memory storage with artifacts under `/relay`.
+    #[cfg(test)]
+    pub(crate) async fn in_memory() -> Self {
+        // The in-memory database lives as long as its only connection.
+        let db = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let store = Self {
+            state: RwLock::new(StorageState { db }),
+            storage_path: PathBuf::from("/relay"),
+            relay_subdirs: HashMap::new(),
+        };
+        store.init().await.unwrap();
+        store
+    }
+
     /// Initialize the storage backend.
     ///
     /// # Errors
     ///
     /// Returns an error if database initialization fails.
     pub async fn init(&self) -> Result<(), StorageManagerError> {
+        let state = self.state.write().await;
+
         sqlx::query(
             r"
 CREATE TABLE IF NOT EXISTS settings (
@@ -87,12 +156,29 @@ CREATE TABLE IF NOT EXISTS tasks (
   name TEXT NOT NULL,
   status INTEGER NOT NULL,
   content_path TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS rss_rules (
+  name TEXT PRIMARY KEY,
+  rule TEXT NOT NULL,
+  from_config INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS seedbox_space (
+  seedbox TEXT PRIMARY KEY,
+  free_space INTEGER NOT NULL,
+  low_space INTEGER NOT NULL DEFAULT 0,
+  updated_at INTEGER NOT NULL
 );
             ",
         )
-        .execute(&self.state.write().await.db)
+        .execute(&state.db)
         .await?;
 
+        for (column, definition) in TASK_COLUMNS {
+            ensure_column(&state.db, "tasks", column, definition).await?;
+        }
+
+        drop(state);
+
         Ok(())
     }
 
@@ -136,6 +222,10 @@ ON CONFLICT(key) DO UPDATE SET value = EXCLUDED.value;
 
     /// Update information about the given torrents.
     ///
+    /// Tasks are keyed by hash alone, so a torrent also present on another
+    /// seedbox is skipped with an error logged, unless its task there is
+    /// missing and the torrent moved.
+    ///
     /// # Errors
     ///
     /// Returns an error if database queries fail.
@@ -146,26 +236,75 @@ ON CONFLICT(key) DO UPDATE SET value = EXCLUDED.value;
         let state = self.state.read().await;
 
         for t in torrents {
+            let owner: Option<(String, i64)> =
+                sqlx::query_as(r"SELECT seedbox, status FROM tasks WHERE hash = $1")
+                    .bind(&t.hash)
+                    .fetch_optional(&state.db)
+                    .await?;
+            if let Some((seedbox, status)) = owner
+                && seedbox != t.seedbox
+                && status != TaskStatus::Missing as i64
+            {
+                tracing::error!(torrent = %t.name, hash = %t.hash, seedbox = %t.seedbox, owner = %seedbox, "Ignoring torrent already tracked on another seedbox");
+                continue;
+            }
+
             let status = match t.status {
-                TorrentStatus::Downloading => TaskStatus::Tracked,
                 TorrentStatus::Seeding => TaskStatus::TorrentReady,
+                _ => TaskStatus::Tracked,
             };
 
             sqlx::query(
                 r"
-INSERT INTO tasks (hash, name, status, content_path)
-VALUES ($1, $2, $3, $4)
+INSERT INTO tasks (hash, name, status, content_path, tag, category, seedbox)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
 ON CONFLICT(hash) DO UPDATE SET
   name = EXCLUDED.name,
   status = EXCLUDED.status,
-  content_path = EXCLUDED.content_path
-WHERE tasks.status < EXCLUDED.status
+  content_path = EXCLUDED.content_path,
+  tag = EXCLUDED.tag,
+  category = EXCLUDED.category,
+  seedbox = EXCLUDED.seedbox
+WHERE tasks.status < EXCLUDED.status OR tasks.status = $8
                 ",
             )
             .bind(&t.hash)
             .bind(&t.name)
             .bind(status as i64)
             .bind(&t.content_path)
+            .bind(&t.tag)
+            .bind(&t.category)
+            .bind(&t.seedbox)
+            // Torrents re-added to the seedbox are tracked again.
+            .bind(TaskStatus::Missing as i64)
+            .execute(&state.db)
+            .await?;
+
+            // Statistics change while the status stays the same. The unhealthy
+            // timer keeps running until the torrent recovers.
+            sqlx::query(
+                r"
+UPDATE tasks
+SET size = $1, progress = $2, eta = $3, dlspeed = $4, num_seeds = $5, state = $6,
+  torrent_status = $7,
+  unhealthy_since = CASE WHEN $8 THEN COALESCE(unhealthy_since, $9) ELSE NULL END,
+  unhealthy_alerted = CASE WHEN $8 THEN unhealthy_alerted ELSE 0 END,
+  completion_on = $11, save_path = $12
+WHERE hash = $10
+                ",
+            )
+            .bind(t.stats.size)
+            .bind(t.stats.progress)
+            .bind(t.stats.eta)
+            .bind(t.stats.dlspeed)
+            .bind(t.stats.num_seeds)
+            .bind(&t.stats.state)
+            .bind(t.status as i64)
+            .bind(t.status.is_unhealthy())
+            .bind(Utc::now().timestamp())
+            .bind(&t.hash)
+            .bind(t.stats.completion_on)
+            .bind(&t.save_path)
             .execute(&state.db)
             .await?;
         }
@@ -175,27 +314,176 @@ WHERE tasks.status < EXCLUDED.status
         Ok(())
     }
 
+    /// Detect torrents whose content changed on the seedbox after their
+    /// artifact was ready, by comparing total size and completion time with
+    /// those recorded when the transfer finished.
+    ///
+    /// With `requeue`, changed tasks go back to [`TaskStatus::TorrentReady`],
+    /// or [`TaskStatus::Tracked`] while the torrent is rechecking or downloading,
+    /// so the new files are transferred. Otherwise they are only reported once.
+    /// Tasks archived by older versions get their current values recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn detect_content_changes(
+        &self,
+        torrents: &[TorrentTaskInfo],
+        requeue: bool,
+    ) -> Result<Vec<TaskSummary>, StorageManagerError> {
+        #[derive(sqlx::FromRow)]
+        struct Row {
+            name: String,
+            tag: String,
+            seedbox: String,
+            transferred_size: Option<i64>,
+            transferred_completion_on: Option<i64>,
+            content_changed: bool,
+        }
+
+        let state = self.state.write().await;
+
+        let mut changed = Vec::new();
+        for t in torrents {
+            let row = sqlx::query_as::<_, Row>(
+                r"
+SELECT name, tag, seedbox, transferred_size, transferred_completion_on, content_changed
+FROM tasks
+WHERE hash = $1 AND status IN ($2, $3) AND seedbox = $4
+                ",
+            )
+            .bind(&t.hash)
+            .bind(TaskStatus::ArtifactReady as i64)
+            .bind(TaskStatus::Archived as i64)
+            .bind(&t.seedbox)
+            .fetch_optional(&state.db)
+            .await?;
+            let Some(row) = row else {
+                continue;
+            };
+
+            if row.transferred_size.is_none() && row.transferred_completion_on.is_none() {
+                sqlx::query(
+                    r"
+UPDATE tasks
+SET transferred_size = size, transferred_completion_on = completion_on
+WHERE hash = $1
+                    ",
+                )
+                .bind(&t.hash)
+                .execute(&state.db)
+                .await?;
+                continue;
+            }
+
+            let differs = |current: Option<i64>, recorded: Option<i64>| matches!((current, recorded), (Some(current), Some(recorded)) if current != recorded);
+            if row.content_changed
+                || !(differs(t.stats.size, row.transferred_size)
+                    || differs(t.stats.completion_on, row.transferred_completion_on))
+            {
+                continue;
+            }
+
+            tracing::info!(torrent = %row.name, hash = %t.hash, requeue = requeue, "Torrent content changed after transfer");
+            if requeue {
+                let status = match t.status {
+                    TorrentStatus::Seeding => TaskStatus::TorrentReady,
+                    _ => TaskStatus::Tracked,
+                };
+                sqlx::query(
+                    r"
+UPDATE tasks
+SET status = $1, cleanup_pending = 0, cleanup_applied = 0, transferred_size = NULL,
+  transferred_completion_on = NULL, content_changed = 0
+WHERE hash = $2
+                    ",
+                )
+                .bind(status as i64)
+                .bind(&t.hash)
+                .execute(&state.db)
+                .await?;
+            } else {
+                sqlx::query(r"UPDATE tasks SET content_changed = 1 WHERE hash = $1")
+                    .bind(&t.hash)
+                    .execute(&state.db)
+                    .await?;
+            }
+
+            changed.push(TaskSummary {
+                hash: t.hash.clone(),
+                name: row.name,
+                tag: row.tag,
+                seedbox: row.seedbox,
+            });
+        }
+
+        drop(state);
+
+        Ok(changed)
+    }
+
     /// List all torrents that are ready for pulling.
     ///
     /// # Errors
     ///
     /// Returns an error if database queries fail.
     pub async fn list_ready_torrents(&self) -> Result<Vec<TorrentTaskInfo>, StorageManagerError> {
+        self.list_torrents(TaskStatus::TorrentReady).await
+    }
+
+    /// List all torrents still downloading on the seedbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_tracked_torrents(&self) -> Result<Vec<TorrentTaskInfo>, StorageManagerError> {
+        self.list_torrents(TaskStatus::Tracked).await
+    }
+
+    /// List torrents of tasks in [`TaskStatus::Tracked`] or [`TaskStatus::TorrentReady`].
+    async fn list_torrents(
+        &self,
+        status: TaskStatus,
+    ) -> Result<Vec<TorrentTaskInfo>, StorageManagerError> {
         #[derive(sqlx::FromRow)]
         struct Row {
             hash: String,
             name: String,
             content_path: String,
+            tag: String,
+            category: Option<String>,
+            size: Option<i64>,
+            progress: f64,
+            eta: Option<i64>,
+            dlspeed: Option<i64>,
+            num_seeds: Option<i64>,
+            state: Option<String>,
+            torrent_status: Option<i64>,
+            seedbox: String,
+            completion_on: Option<i64>,
+            save_path: Option<String>,
         }
 
+        // Rows created by older versions have no torrent status.
+        let default_status = match status {
+            TaskStatus::Tracked => TorrentStatus::Downloading,
+            TaskStatus::TorrentReady => TorrentStatus::Seeding,
+            _ => {
+                return Err(StorageManagerError::InvalidState(format!(
+                    "{status:?} tasks have no torrent"
+                )));
+            }
+        };
+
         let rows = sqlx::query_as::<_, Row>(
             r"
-SELECT hash, name, content_path
+SELECT hash, name, content_path, tag, category, size, progress, eta, dlspeed, num_seeds, state,
+  torrent_status, seedbox, completion_on, save_path
 FROM tasks
 WHERE status = $1
             ",
         )
-        .bind(TaskStatus::TorrentReady as i64)
+        .bind(status as i64)
         .fetch_all(&self.state.read().await.db)
         .await?;
 
@@ -204,8 +492,24 @@ WHERE status = $1
             .map(|row| TorrentTaskInfo {
                 hash: row.hash,
                 name: row.name,
-                status: TorrentStatus::Seeding,
+                status: row
+                    .torrent_status
+                    .and_then(|status| TorrentStatus::try_from(status).ok())
+                    .unwrap_or(default_status),
                 content_path: row.content_path,
+                tag: row.tag,
+                category: row.category,
+                save_path: row.save_path,
+                stats: TorrentStats {
+                    size: row.size,
+                    progress: row.progress,
+                    eta: row.eta,
+                    dlspeed: row.dlspeed,
+                    num_seeds: row.num_seeds,
+                    state: row.state,
+                    completion_on: row.completion_on,
+                },
+                seedbox: row.seedbox,
             })
             .collect();
 
@@ -223,7 +527,9 @@ WHERE status = $1
         let _result = sqlx::query(
             r"
 UPDATE tasks
-SET status = $1
+SET status = $1, transferred_size = size, transferred_completion_on = completion_on,
+  content_changed = 0, transfer_attempts = 0, last_error = NULL, next_attempt_at = NULL,
+  transfer_held = 0
 WHERE hash = $2 AND status = $3
             ",
         )
@@ -239,6 +545,216 @@ WHERE hash = $2 AND status = $3
         Ok(())
     }
 
+    /// Record a failed transfer attempt of a ready torrent along with its error.
+    ///
+    /// `retry_at` maps the number of failed attempts so far to the earliest
+    /// time of the next attempt. If it returns `None`, the task is moved to
+    /// [`TaskStatus::Failed`] until retried manually.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn record_transfer_failure(
+        &self,
+        hash: &str,
+        error: &str,
+        retry_at: impl FnOnce(u32) -> Option<DateTime<Utc>>,
+    ) -> Result<TransferFailure, StorageManagerError> {
+        let state = self.state.write().await;
+
+        let attempts: Option<(i64,)> =
+            sqlx::query_as(r"SELECT transfer_attempts FROM tasks WHERE hash = $1 AND status = $2")
+                .bind(hash)
+                .bind(TaskStatus::TorrentReady as i64)
+                .fetch_optional(&state.db)
+                .await?;
+        let Some((attempts,)) = attempts else {
+            return Err(StorageManagerError::InvalidState(format!(
+                "task {hash} is not ready for transfer"
+            )));
+        };
+
+        let attempts = u32::try_from(attempts).unwrap_or(0).saturating_add(1);
+        let retry_at = retry_at(attempts);
+        let status = match retry_at {
+            Some(_) => TaskStatus::TorrentReady,
+            None => TaskStatus::Failed,
+        };
+        sqlx::query(
+            r"
+UPDATE tasks
+SET status = $1, transfer_attempts = $2, last_error = $3, next_attempt_at = $4,
+  transfer_held = 0
+WHERE hash = $5
+            ",
+        )
+        .bind(status as i64)
+        .bind(i64::from(attempts))
+        .bind(error)
+        .bind(retry_at.map(|at| at.timestamp()))
+        .bind(hash)
+        .execute(&state.db)
+        .await?;
+
+        drop(state);
+
+        Ok(TransferFailure { attempts, retry_at })
+    }
+
+    /// Hold a ready torrent back from scheduled transfers after its transfer
+    /// was cancelled, until it is transferred on request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn hold_transfer(&self, hash: &str) -> Result<(), StorageManagerError> {
+        sqlx::query(r"UPDATE tasks SET transfer_held = 1 WHERE hash = $1 AND status = $2")
+            .bind(hash)
+            .bind(TaskStatus::TorrentReady as i64)
+            .execute(&self.state.write().await.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Hashes of ready torrents whose next transfer attempt is after `now`,
+    /// or which are held after a cancelled transfer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_deferred_transfers(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<HashSet<String>, StorageManagerError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r"
+SELECT hash
+FROM tasks
+WHERE status = $1 AND (next_attempt_at > $2 OR transfer_held = 1)
+            ",
+        )
+        .bind(TaskStatus::TorrentReady as i64)
+        .bind(now.timestamp())
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        Ok(rows.into_iter().map(|(hash,)| hash).collect())
+    }
+
+    /// List tasks whose transfer failed too many times.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_failed_tasks(&self) -> Result<Vec<FailedTask>, StorageManagerError> {
+        let rows: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(
+            r"
+SELECT hash, name, transfer_attempts, last_error
+FROM tasks
+WHERE status = $1
+            ",
+        )
+        .bind(TaskStatus::Failed as i64)
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        let tasks = rows
+            .into_iter()
+            .map(|(hash, name, attempts, last_error)| FailedTask {
+                hash,
+                name,
+                attempts: u32::try_from(attempts).unwrap_or(0),
+                last_error,
+            })
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Move failed tasks whose hash starts with `prefix`, or all failed tasks
+    /// without a prefix, back to [`TaskStatus::TorrentReady`] with a fresh
+    /// retry budget.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn retry_failed_tasks(
+        &self,
+        prefix: Option<&str>,
+    ) -> Result<Vec<TaskSummary>, StorageManagerError> {
+        let state = self.state.write().await;
+
+        let prefix = prefix.unwrap_or_default().to_lowercase();
+        let rows: Vec<(String, String, String, String)> = sqlx::query_as(
+            r"
+SELECT hash, name, tag, seedbox
+FROM tasks
+WHERE status = $1 AND substr(hash, 1, length($2)) = $2
+            ",
+        )
+        .bind(TaskStatus::Failed as i64)
+        .bind(&prefix)
+        .fetch_all(&state.db)
+        .await?;
+
+        for (hash, ..) in &rows {
+            sqlx::query(
+                r"
+UPDATE tasks
+SET status = $1, transfer_attempts = 0, last_error = NULL, next_attempt_at = NULL
+WHERE hash = $2
+                ",
+            )
+            .bind(TaskStatus::TorrentReady as i64)
+            .bind(hash)
+            .execute(&state.db)
+            .await?;
+        }
+
+        drop(state);
+
+        let tasks = rows
+            .into_iter()
+            .map(|(hash, name, tag, seedbox)| TaskSummary {
+                hash,
+                name,
+                tag,
+                seedbox,
+            })
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Record the files selected for transfer of a torrent, as a JSON array.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn record_manifest(
+        &self,
+        hash: &str,
+        files: &[String],
+    ) -> Result<(), StorageManagerError> {
+        tracing::trace!(hash = %hash, count = files.len(), "Recording transfer manifest");
+
+        let manifest = serde_json::to_string(files)?;
+        sqlx::query(
+            r"
+UPDATE tasks
+SET manifest = $1
+WHERE hash = $2
+            ",
+        )
+        .bind(manifest)
+        .bind(hash)
+        .execute(&self.state.write().await.db)
+        .await?;
+
+        Ok(())
+    }
+
     /// Returns current task status for a torrent hash.
     ///
     /// # Errors
@@ -289,11 +805,13 @@ WHERE hash = $1
         struct Row {
             hash: String,
             name: String,
+            tag: String,
+            category: Option<String>,
         }
 
         let rows = sqlx::query_as::<_, Row>(
             r"
-SELECT hash, name
+SELECT hash, name, tag, category
 FROM tasks
 WHERE status = $1
             ",
@@ -306,7 +824,7 @@ WHERE status = $1
             .into_iter()
             .map(|row| {
                 let path = self
-                    .artifact_storage_path(&row.hash)
+                    .artifact_storage_path(&row.hash, &row.tag, row.category.as_deref())
                     .to_string_lossy()
                     .into();
 
@@ -314,6 +832,7 @@ WHERE status = $1
                     hash: row.hash,
                     name: row.name,
                     path,
+                    category: row.category,
                 }
             })
             .collect();
@@ -321,9 +840,27 @@ WHERE status = $1
         Ok(artifacts)
     }
 
-    /// Get the path of artifact on relay from the given hash.
-    pub fn artifact_storage_path(&self, hash: &str) -> PathBuf {
-        self.storage_path.join("artifacts").join(hash)
+    /// Get the path of artifact on relay from the given hash, its tag and category.
+    ///
+    /// Artifacts are laid out as `artifacts/[<relay_subdir>/][<category>/]<hash>`.
+    /// Categories which are not plain relative paths are ignored.
+    pub fn artifact_storage_path(&self, hash: &str, tag: &str, category: Option<&str>) -> PathBuf {
+        let mut path = self.storage_path.join("artifacts");
+        if let Some(subdir) = self.relay_subdirs.get(tag) {
+            path.push(subdir);
+        }
+        if let Some(category) = category {
+            let category = Path::new(category);
+            if category
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+            {
+                path.push(category);
+            } else {
+                tracing::warn!(hash = %hash, category = %category.display(), "Ignoring category unsuitable as directory name");
+            }
+        }
+        path.join(hash)
     }
 
     /// Prepare the artifact storage directory.
@@ -331,8 +868,13 @@ WHERE status = $1
     /// # Errors
     ///
     /// Returns an error if directory creation fails.
-    pub async fn prepare_artifact_storage(&self, hash: &str) -> Result<(), StorageManagerError> {
-        let path = self.artifact_storage_path(hash);
+    pub async fn prepare_artifact_storage(
+        &self,
+        hash: &str,
+        tag: &str,
+        category: Option<&str>,
+    ) -> Result<(), StorageManagerError> {
+        let path = self.artifact_storage_path(hash, tag, category);
         tracing::trace!(hash = %hash, path = %path.display(), "Preparing artifact storage directory");
 
         tokio::fs::create_dir_all(path)
@@ -352,21 +894,22 @@ WHERE status = $1
     pub async fn finalize_artifact(&self, hash: &str) -> Result<(), FinalizeArtifactError> {
         tracing::info!(hash = %hash, "Finalizing artifact");
 
-        let result = sqlx::query(
+        let archived: Option<(String, Option<String>)> = sqlx::query_as(
             r"
 UPDATE tasks
-SET status = $1
+SET status = $1, cleanup_pending = 1, cleanup_applied = 0
 WHERE hash = $2 AND status = $3
+RETURNING tag, category
             ",
         )
         .bind(TaskStatus::Archived as i64)
         .bind(hash)
         .bind(TaskStatus::ArtifactReady as i64)
-        .execute(&self.state.write().await.db)
+        .fetch_optional(&self.state.write().await.db)
         .await
         .map_err(|e| FinalizeArtifactError::Storage(anyhow::anyhow!(e)))?;
 
-        if result.rows_affected() == 0 {
+        let Some((tag, category)) = archived else {
             let already_archived = sqlx::query(
                 r"
 SELECT 1 FROM tasks WHERE hash = $1 AND status = $2
@@ -384,9 +927,9 @@ SELECT 1 FROM tasks WHERE hash = $1 AND status = $2
             } else {
                 Err(FinalizeArtifactError::NotFound)
             };
-        }
+        };
 
-        let path = self.artifact_storage_path(hash);
+        let path = self.artifact_storage_path(hash, &tag, category.as_deref());
         tracing::info!(path = %path.display(), "Removing artifact storage directory");
 
         tokio::fs::remove_dir_all(path)
@@ -395,6 +938,430 @@ SELECT 1 FROM tasks WHERE hash = $1 AND status = $2
 
         Ok(())
     }
+
+    /// List archived tasks waiting for seedbox-side cleanup.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_pending_cleanups(&self) -> Result<Vec<PendingCleanup>, StorageManagerError> {
+        let rows: Vec<(String, String, String, String, bool)> = sqlx::query_as(
+            r"
+SELECT hash, name, tag, seedbox, cleanup_applied
+FROM tasks
+WHERE status = $1 AND cleanup_pending = 1
+            ",
+        )
+        .bind(TaskStatus::Archived as i64)
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        let tasks = rows
+            .into_iter()
+            .map(
+                |(hash, name, tag, seedbox, actions_applied)| PendingCleanup {
+                    task: TaskSummary {
+                        hash,
+                        name,
+                        tag,
+                        seedbox,
+                    },
+                    actions_applied,
+                },
+            )
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Move tasks whose torrent is no longer on the given seedbox to
+    /// [`TaskStatus::Missing`].
+    ///
+    /// Only tasks of that seedbox still depending on it, i.e. [`TaskStatus::Tracked`],
+    /// [`TaskStatus::TorrentReady`] and [`TaskStatus::Failed`], are considered.
+    /// Returns the tasks which just became missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn reconcile_missing(
+        &self,
+        seedbox: &str,
+        present: &HashSet<String>,
+    ) -> Result<Vec<TaskSummary>, StorageManagerError> {
+        let state = self.state.write().await;
+
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            r"
+SELECT hash, name, tag
+FROM tasks
+WHERE status IN ($1, $2, $3) AND seedbox = $4
+            ",
+        )
+        .bind(TaskStatus::Tracked as i64)
+        .bind(TaskStatus::TorrentReady as i64)
+        .bind(TaskStatus::Failed as i64)
+        .bind(seedbox)
+        .fetch_all(&state.db)
+        .await?;
+
+        let mut missing = Vec::new();
+        for (hash, name, tag) in rows {
+            if present.contains(&hash) {
+                continue;
+            }
+
+            tracing::info!(torrent = %name, hash = %hash, seedbox = %seedbox, "Torrent vanished from seedbox");
+            // A torrent added again was not paused by anipler.
+            sqlx::query(r"UPDATE tasks SET status = $1, paused = 0 WHERE hash = $2")
+                .bind(TaskStatus::Missing as i64)
+                .bind(&hash)
+                .execute(&state.db)
+                .await?;
+
+            missing.push(TaskSummary {
+                hash,
+                name,
+                tag,
+                seedbox: seedbox.to_string(),
+            });
+        }
+
+        drop(state);
+
+        Ok(missing)
+    }
+
+    /// List tracked torrents unhealthy since before `threshold` which have not
+    /// been alerted yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_unhealthy_tasks(
+        &self,
+        threshold: DateTime<Utc>,
+    ) -> Result<Vec<UnhealthyTask>, StorageManagerError> {
+        let rows: Vec<(String, String, i64, i64)> = sqlx::query_as(
+            r"
+SELECT hash, name, torrent_status, unhealthy_since
+FROM tasks
+WHERE status = $1 AND unhealthy_since <= $2 AND unhealthy_alerted = 0
+            ",
+        )
+        .bind(TaskStatus::Tracked as i64)
+        .bind(threshold.timestamp())
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|(hash, name, status, since)| {
+                let status = TorrentStatus::try_from(status)
+                    .map_err(|e| StorageManagerError::InvalidState(e.to_string()))?;
+                let since = DateTime::from_timestamp(since, 0).ok_or_else(|| {
+                    StorageManagerError::InvalidState(format!("invalid timestamp {since}"))
+                })?;
+                Ok(UnhealthyTask {
+                    hash,
+                    name,
+                    status,
+                    since,
+                })
+            })
+            .collect()
+    }
+
+    /// Record that an alert was sent for an unhealthy torrent, until it recovers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn mark_unhealthy_alerted(&self, hash: &str) -> Result<(), StorageManagerError> {
+        sqlx::query(r"UPDATE tasks SET unhealthy_alerted = 1 WHERE hash = $1")
+            .bind(hash)
+            .execute(&self.state.write().await.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List tasks whose torrent vanished from the seedbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_missing_tasks(&self) -> Result<Vec<TaskSummary>, StorageManagerError> {
+        let rows: Vec<(String, String, String, String)> = sqlx::query_as(
+            r"
+SELECT hash, name, tag, seedbox
+FROM tasks
+WHERE status = $1
+            ",
+        )
+        .bind(TaskStatus::Missing as i64)
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        let tasks = rows
+            .into_iter()
+            .map(|(hash, name, tag, seedbox)| TaskSummary {
+                hash,
+                name,
+                tag,
+                seedbox,
+            })
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Record the free space of a seedbox and whether it is below the alert threshold.
+    ///
+    /// Returns whether the seedbox was already low on space, so that the
+    /// alert is only sent once per episode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn record_free_space(
+        &self,
+        seedbox: &str,
+        free_space: u64,
+        low_space: bool,
+    ) -> Result<bool, StorageManagerError> {
+        let state = self.state.write().await;
+
+        let was_low: Option<(bool,)> =
+            sqlx::query_as(r"SELECT low_space FROM seedbox_space WHERE seedbox = $1")
+                .bind(seedbox)
+                .fetch_optional(&state.db)
+                .await?;
+
+        sqlx::query(
+            r"
+INSERT INTO seedbox_space (seedbox, free_space, low_space, updated_at)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT(seedbox) DO UPDATE SET
+  free_space = EXCLUDED.free_space,
+  low_space = EXCLUDED.low_space,
+  updated_at = EXCLUDED.updated_at
+            ",
+        )
+        .bind(seedbox)
+        .bind(i64::try_from(free_space).unwrap_or(i64::MAX))
+        .bind(low_space)
+        .bind(Utc::now().timestamp())
+        .execute(&state.db)
+        .await?;
+
+        drop(state);
+
+        Ok(was_low.is_some_and(|(low,)| low))
+    }
+
+    /// List the last free space reported by each seedbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_free_space(&self) -> Result<Vec<SeedboxSpace>, StorageManagerError> {
+        let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+            r"
+SELECT seedbox, free_space, updated_at
+FROM seedbox_space
+ORDER BY seedbox
+            ",
+        )
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|(seedbox, free_space, updated_at)| {
+                let updated_at = DateTime::from_timestamp(updated_at, 0).ok_or_else(|| {
+                    StorageManagerError::InvalidState(format!("invalid timestamp {updated_at}"))
+                })?;
+                Ok(SeedboxSpace {
+                    seedbox,
+                    free_space: free_space.max(0).unsigned_abs(),
+                    updated_at,
+                })
+            })
+            .collect()
+    }
+
+    /// List hashes of tracked torrents on a seedbox, either paused by anipler
+    /// or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_tracked_hashes(
+        &self,
+        seedbox: &str,
+        paused: bool,
+    ) -> Result<Vec<String>, StorageManagerError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r"
+SELECT hash
+FROM tasks
+WHERE status = $1 AND seedbox = $2 AND paused = $3
+            ",
+        )
+        .bind(TaskStatus::Tracked as i64)
+        .bind(seedbox)
+        .bind(paused)
+        .fetch_all(&self.state.read().await.db)
+        .await?;
+
+        Ok(rows.into_iter().map(|(hash,)| hash).collect())
+    }
+
+    /// Record whether torrents are paused by anipler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn set_paused(
+        &self,
+        hashes: &[String],
+        paused: bool,
+    ) -> Result<(), StorageManagerError> {
+        let state = self.state.write().await;
+
+        for hash in hashes {
+            sqlx::query(r"UPDATE tasks SET paused = $1 WHERE hash = $2")
+                .bind(paused)
+                .bind(hash)
+                .execute(&state.db)
+                .await?;
+        }
+
+        drop(state);
+
+        Ok(())
+    }
+
+    /// List RSS rules managed by anipler, ordered by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn list_rss_rules(&self) -> Result<Vec<ManagedRssRule>, StorageManagerError> {
+        let rows: Vec<(String, bool)> =
+            sqlx::query_as(r"SELECT rule, from_config FROM rss_rules ORDER BY name")
+                .fetch_all(&self.state.read().await.db)
+                .await?;
+
+        rows.into_iter()
+            .map(|(rule, from_config)| {
+                Ok(ManagedRssRule {
+                    rule: serde_json::from_str(&rule)?,
+                    from_config,
+                })
+            })
+            .collect()
+    }
+
+    /// Create or replace an RSS rule managed by anipler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn save_rss_rule(
+        &self,
+        rule: &RssRule,
+        from_config: bool,
+    ) -> Result<(), StorageManagerError> {
+        sqlx::query(
+            r"
+INSERT INTO rss_rules (name, rule, from_config)
+VALUES ($1, $2, $3)
+ON CONFLICT(name) DO UPDATE SET
+  rule = EXCLUDED.rule,
+  from_config = EXCLUDED.from_config
+            ",
+        )
+        .bind(&rule.name)
+        .bind(serde_json::to_string(rule)?)
+        .bind(from_config)
+        .execute(&self.state.write().await.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget an RSS rule managed by anipler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn delete_rss_rule(&self, name: &str) -> Result<(), StorageManagerError> {
+        sqlx::query(r"DELETE FROM rss_rules WHERE name = $1")
+            .bind(name)
+            .execute(&self.state.write().await.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that the tags and share limits of an archived task were applied,
+    /// so that only its deletion is retried.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn mark_cleanup_applied(&self, hash: &str) -> Result<(), StorageManagerError> {
+        tracing::trace!(hash = %hash, "Marking post-archive actions as applied");
+
+        sqlx::query(r"UPDATE tasks SET cleanup_applied = 1 WHERE hash = $1")
+            .bind(hash)
+            .execute(&self.state.write().await.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark the seedbox-side cleanup of an archived task as finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if database queries fail.
+    pub async fn finish_cleanup(&self, hash: &str) -> Result<(), StorageManagerError> {
+        tracing::trace!(hash = %hash, "Marking cleanup as finished");
+
+        sqlx::query(r"UPDATE tasks SET cleanup_pending = 0 WHERE hash = $1")
+            .bind(hash)
+            .execute(&self.state.write().await.db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Add a column to an existing table unless it is already present.
+async fn ensure_column(
+    db: &sqlx::sqlite::SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), StorageManagerError> {
+    let exists: Option<(i64,)> =
+        sqlx::query_as(r"SELECT 1 FROM pragma_table_info($1) WHERE name = $2")
+            .bind(table)
+            .bind(column)
+            .fetch_optional(db)
+            .await?;
+
+    if exists.is_none() {
+        tracing::info!(table = %table, column = %column, "Migrating database schema");
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))
+        .execute(db)
+        .await?;
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -407,4 +1374,346 @@ pub enum StorageManagerError {
     Chrono(#[from] chrono::ParseError),
     #[error("Invalid state: {0}")]
     InvalidState(String),
+    #[error("Serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn memory_store() -> StorageManager {
+        StorageManager::in_memory().await
+    }
+
+    fn torrent(hash: &str, status: TorrentStatus) -> TorrentTaskInfo {
+        TorrentTaskInfo {
+            hash: hash.to_string(),
+            status,
+            content_path: format!("/downloads/{hash}"),
+            name: format!("Torrent {hash}"),
+            tag: "anipler".to_string(),
+            category: None,
+            save_path: Some("/downloads".to_string()),
+            stats: TorrentStats {
+                size: Some(100),
+                progress: if status == TorrentStatus::Seeding {
+                    1.0
+                } else {
+                    0.5
+                },
+                completion_on: Some(1_000),
+                ..TorrentStats::default()
+            },
+            seedbox: "default".to_string(),
+        }
+    }
+
+    async fn status(store: &StorageManager, hash: &str) -> Option<TaskStatus> {
+        store.task_status_by_hash(hash).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn vanished_tasks_become_missing_until_seen_again() {
+        let store = memory_store().await;
+        let mut other = torrent("cccc", TorrentStatus::Downloading);
+        other.seedbox = "other".to_string();
+        store
+            .update_torrent_info(&[
+                torrent("aaaa", TorrentStatus::Seeding),
+                torrent("bbbb", TorrentStatus::Downloading),
+                other,
+            ])
+            .await
+            .unwrap();
+
+        store.set_paused(&["bbbb".to_string()], true).await.unwrap();
+
+        let present = HashSet::from(["aaaa".to_string()]);
+        let missing = store.reconcile_missing("default", &present).await.unwrap();
+        assert_eq!(
+            missing.iter().map(|t| t.hash.as_str()).collect::<Vec<_>>(),
+            ["bbbb"]
+        );
+        assert_eq!(status(&store, "bbbb").await, Some(TaskStatus::Missing));
+        assert_eq!(status(&store, "cccc").await, Some(TaskStatus::Tracked));
+        // Only reported once.
+        assert!(
+            store
+                .reconcile_missing("default", &present)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        store
+            .update_torrent_info(&[torrent("bbbb", TorrentStatus::Downloading)])
+            .await
+            .unwrap();
+        assert_eq!(status(&store, "bbbb").await, Some(TaskStatus::Tracked));
+        assert!(
+            store
+                .list_tracked_hashes("default", true)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        store
+            .update_torrent_info(&[torrent("bbbb", TorrentStatus::Seeding)])
+            .await
+            .unwrap();
+        assert_eq!(status(&store, "bbbb").await, Some(TaskStatus::TorrentReady));
+    }
+
+    #[tokio::test]
+    async fn tasks_with_ready_artifacts_are_never_missing() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[torrent("aaaa", TorrentStatus::Seeding)])
+            .await
+            .unwrap();
+        store.mark_artifact_ready("aaaa").await.unwrap();
+
+        let missing = store
+            .reconcile_missing("default", &HashSet::new())
+            .await
+            .unwrap();
+
+        assert!(missing.is_empty());
+        assert_eq!(
+            status(&store, "aaaa").await,
+            Some(TaskStatus::ArtifactReady)
+        );
+    }
+
+    #[tokio::test]
+    async fn content_changes_are_reported_once_without_requeue() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[torrent("aaaa", TorrentStatus::Seeding)])
+            .await
+            .unwrap();
+        store.mark_artifact_ready("aaaa").await.unwrap();
+
+        let unchanged = torrent("aaaa", TorrentStatus::Seeding);
+        assert!(
+            store
+                .detect_content_changes(&[unchanged], false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let mut grown = torrent("aaaa", TorrentStatus::Seeding);
+        grown.stats.size = Some(200);
+        let changed = store
+            .detect_content_changes(std::slice::from_ref(&grown), false)
+            .await
+            .unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            status(&store, "aaaa").await,
+            Some(TaskStatus::ArtifactReady)
+        );
+        assert!(
+            store
+                .detect_content_changes(&[grown], false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn changed_content_is_requeued() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[
+                torrent("aaaa", TorrentStatus::Seeding),
+                torrent("bbbb", TorrentStatus::Seeding),
+            ])
+            .await
+            .unwrap();
+        store.mark_artifact_ready("aaaa").await.unwrap();
+        store.mark_artifact_ready("bbbb").await.unwrap();
+
+        let mut completed_again = torrent("aaaa", TorrentStatus::Seeding);
+        completed_again.stats.completion_on = Some(2_000);
+        let mut rechecking = torrent("bbbb", TorrentStatus::Downloading);
+        rechecking.stats.size = Some(200);
+        let changed = store
+            .detect_content_changes(&[completed_again, rechecking], true)
+            .await
+            .unwrap();
+
+        assert_eq!(changed.len(), 2);
+        assert_eq!(status(&store, "aaaa").await, Some(TaskStatus::TorrentReady));
+        assert_eq!(status(&store, "bbbb").await, Some(TaskStatus::Tracked));
+    }
+
+    #[tokio::test]
+    async fn transfer_failures_back_off_until_attempts_run_out() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[torrent("aaaa", TorrentStatus::Seeding)])
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let backoff = |attempts: u32| {
+            (attempts < 3).then(|| now + chrono::TimeDelta::minutes(i64::from(attempts) * 10))
+        };
+
+        let first = store
+            .record_transfer_failure("aaaa", "timeout", backoff)
+            .await
+            .unwrap();
+        assert_eq!(first.attempts, 1);
+        assert_eq!(
+            first.retry_at.map(|at| at.timestamp()),
+            Some((now + chrono::TimeDelta::minutes(10)).timestamp())
+        );
+        assert!(
+            store
+                .list_deferred_transfers(now)
+                .await
+                .unwrap()
+                .contains("aaaa")
+        );
+        assert!(
+            store
+                .list_deferred_transfers(now + chrono::TimeDelta::minutes(15))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let second = store
+            .record_transfer_failure("aaaa", "timeout", backoff)
+            .await
+            .unwrap();
+        assert_eq!(second.attempts, 2);
+        assert!(second.retry_at > first.retry_at);
+        assert_eq!(status(&store, "aaaa").await, Some(TaskStatus::TorrentReady));
+
+        let last = store
+            .record_transfer_failure("aaaa", "connection refused", backoff)
+            .await
+            .unwrap();
+        assert_eq!(last.attempts, 3);
+        assert_eq!(last.retry_at, None);
+        assert_eq!(status(&store, "aaaa").await, Some(TaskStatus::Failed));
+        let failed = store.list_failed_tasks().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, 3);
+        assert_eq!(failed[0].last_error.as_deref(), Some("connection refused"));
+
+        assert!(matches!(
+            store
+                .record_transfer_failure("aaaa", "timeout", backoff)
+                .await,
+            Err(StorageManagerError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_tasks_are_retried_by_hash_prefix() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[
+                torrent("abc123", TorrentStatus::Seeding),
+                torrent("abd456", TorrentStatus::Seeding),
+            ])
+            .await
+            .unwrap();
+        for hash in ["abc123", "abd456"] {
+            store
+                .record_transfer_failure(hash, "permission denied", |_| None)
+                .await
+                .unwrap();
+        }
+
+        let retried = store.retry_failed_tasks(Some("ABC")).await.unwrap();
+        assert_eq!(
+            retried.iter().map(|t| t.hash.as_str()).collect::<Vec<_>>(),
+            ["abc123"]
+        );
+        assert_eq!(
+            status(&store, "abc123").await,
+            Some(TaskStatus::TorrentReady)
+        );
+        assert_eq!(status(&store, "abd456").await, Some(TaskStatus::Failed));
+        // The retry budget starts over.
+        let failure = store
+            .record_transfer_failure("abc123", "timeout", |_| Some(Utc::now()))
+            .await
+            .unwrap();
+        assert_eq!(failure.attempts, 1);
+
+        let retried = store.retry_failed_tasks(None).await.unwrap();
+        assert_eq!(
+            retried.iter().map(|t| t.hash.as_str()).collect::<Vec<_>>(),
+            ["abd456"]
+        );
+        assert!(store.list_failed_tasks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn applied_post_archive_actions_are_not_repeated() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[torrent("aaaa", TorrentStatus::Seeding)])
+            .await
+            .unwrap();
+        store.mark_artifact_ready("aaaa").await.unwrap();
+        // Archived by the user, the artifact directory is not needed here.
+        sqlx::query("UPDATE tasks SET status = $1, cleanup_pending = 1 WHERE hash = 'aaaa'")
+            .bind(TaskStatus::Archived as i64)
+            .execute(&store.state.read().await.db)
+            .await
+            .unwrap();
+
+        let pending = store.list_pending_cleanups().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(!pending[0].actions_applied);
+
+        store.mark_cleanup_applied("aaaa").await.unwrap();
+        let pending = store.list_pending_cleanups().await.unwrap();
+        assert!(pending[0].actions_applied);
+
+        store.finish_cleanup("aaaa").await.unwrap();
+        assert!(store.list_pending_cleanups().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicate_hash_on_another_seedbox_is_ignored() {
+        let store = memory_store().await;
+        store
+            .update_torrent_info(&[torrent("aaaa", TorrentStatus::Downloading)])
+            .await
+            .unwrap();
+
+        let mut duplicate = torrent("aaaa", TorrentStatus::Seeding);
+        duplicate.seedbox = "other".to_string();
+        duplicate.content_path = "/other/aaaa".to_string();
+        store
+            .update_torrent_info(std::slice::from_ref(&duplicate))
+            .await
+            .unwrap();
+
+        let tracked = store.list_tracked_torrents().await.unwrap();
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].seedbox, "default");
+        assert_eq!(tracked[0].content_path, "/downloads/aaaa");
+
+        // Torrents moved to another seedbox are tracked there.
+        store
+            .reconcile_missing("default", &HashSet::new())
+            .await
+            .unwrap();
+        store.update_torrent_info(&[duplicate]).await.unwrap();
+        let ready = store.list_ready_torrents().await.unwrap();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].seedbox, "other");
+    }
 }
@@ -1,13 +1,43 @@
 use std::fmt::Display;
 
-pub use crate::rsync::TransferState;
+pub use crate::rsync::{TransferProgress, TransferState};
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct TorrentTaskInfo {
     pub hash: String,
     pub status: TorrentStatus,
     pub content_path: String,
     pub name: String,
+    /// Tracked tag the torrent matched.
+    pub tag: String,
+    /// qBittorrent category, if any.
+    pub category: Option<String>,
+    /// Directory the files of the torrent are relative to, if the backend
+    /// reports it.
+    pub save_path: Option<String>,
+    pub stats: TorrentStats,
+    /// Name of the seedbox holding the torrent, assigned by the daemon since
+    /// backends do not know their configured name.
+    pub seedbox: String,
+}
+
+/// Download statistics of a torrent, as far as the seedbox backend reports them.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct TorrentStats {
+    /// Total size of the selected files in bytes.
+    pub size: Option<i64>,
+    /// Download progress in `0.0..=1.0`.
+    pub progress: f64,
+    /// Estimated time to completion in seconds.
+    pub eta: Option<i64>,
+    /// Download speed in bytes per second.
+    pub dlspeed: Option<i64>,
+    /// Number of connected seeds.
+    pub num_seeds: Option<i64>,
+    /// Backend-specific torrent state, e.g. `stalledDL` for qBittorrent.
+    pub state: Option<String>,
+    /// Unix timestamp of the last download completion.
+    pub completion_on: Option<i64>,
 }
 
 #[derive(Clone, Debug)]
@@ -20,6 +50,34 @@ pub struct TransferTaskInfo {
     pub dest: String,
     /// Human-readable torrent name.
     pub name: String,
+    /// Tracked tag of the torrent.
+    pub tag: String,
+    /// Seedbox category of the torrent.
+    pub category: Option<String>,
+    /// rsync bandwidth limit in KiB/s.
+    pub speed_limit: Option<u32>,
+    /// Name of the seedbox to transfer from.
+    pub seedbox: String,
+    /// Save path of the torrent on the seedbox, if known.
+    pub save_path: Option<String>,
+}
+
+impl TransferTaskInfo {
+    /// Seedbox directory the files of a manifest are relative to.
+    ///
+    /// This is the save path of the torrent, which differs from the parent of
+    /// the source for multi-file torrents without a root folder. The parent of
+    /// the source is assumed if the save path is unknown.
+    pub fn manifest_root(&self) -> Option<&str> {
+        match &self.save_path {
+            Some(save_path) => Some(save_path.trim_end_matches('/')),
+            None => self
+                .source
+                .trim_end_matches('/')
+                .rsplit_once('/')
+                .map(|(parent, _)| parent),
+        }
+    }
 }
 
 impl Display for TorrentTaskInfo {
@@ -28,10 +86,120 @@ impl Display for TorrentTaskInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, num_enum::TryFromPrimitive)]
+#[repr(i64)]
 pub enum TorrentStatus {
-    Downloading,
-    Seeding,
+    Downloading = 0,
+    Seeding = 1,
+    /// Downloading without any connected peer sending data.
+    Stalled = 2,
+    /// Waiting for metadata of a magnet link.
+    FetchingMetadata = 3,
+    /// The torrent client reported an error, e.g. an I/O failure.
+    Errored = 4,
+    /// Files of the torrent are gone from the disk of the seedbox.
+    MissingFiles = 5,
+}
+
+impl TorrentStatus {
+    /// Whether the torrent needs attention if it stays in this status.
+    pub const fn is_unhealthy(self) -> bool {
+        !matches!(self, Self::Downloading | Self::Seeding)
+    }
+}
+
+impl Display for TorrentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Downloading => "downloading",
+            Self::Seeding => "seeding",
+            Self::Stalled => "stalled",
+            Self::FetchingMetadata => "fetching metadata",
+            Self::Errored => "errored",
+            Self::MissingFiles => "missing files",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Tracked torrent which has been unhealthy for a while.
+#[derive(Debug, Clone)]
+pub struct UnhealthyTask {
+    pub hash: String,
+    pub name: String,
+    pub status: TorrentStatus,
+    /// When the torrent entered an unhealthy status.
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
+/// Outcome of recording a failed transfer attempt.
+#[derive(Debug, Clone, Copy)]
+pub struct TransferFailure {
+    /// Failed attempts so far.
+    pub attempts: u32,
+    /// Earliest time of the next attempt, `None` if the task is marked failed.
+    pub retry_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Task whose transfer failed too many times.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FailedTask {
+    pub hash: String,
+    pub name: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Archived task waiting for seedbox-side cleanup.
+#[derive(Debug, Clone)]
+pub struct PendingCleanup {
+    pub task: TaskSummary,
+    /// Whether the tags and share limits were already applied, leaving only
+    /// the deletion once the share limits are reached.
+    pub actions_applied: bool,
+}
+
+/// Result of adopting torrents added before the earliest import date.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct AdoptOutcome {
+    /// Torrents imported as ready.
+    pub adopted: Vec<TaskSummary>,
+    /// Selected torrents skipped because they are still downloading.
+    pub incomplete: Vec<TaskSummary>,
+}
+
+/// RSS rule synced to a seedbox by anipler.
+#[derive(Debug, Clone)]
+pub struct ManagedRssRule {
+    pub rule: crate::config::RssRule,
+    /// Whether the rule comes from `[[rss]]` rather than the bot.
+    pub from_config: bool,
+}
+
+/// Last free space reported by a seedbox.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SeedboxSpace {
+    pub seedbox: String,
+    /// Free space in bytes.
+    pub free_space: u64,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Identifying information of a stored task.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TaskSummary {
+    pub hash: String,
+    pub name: String,
+    pub tag: String,
+    pub seedbox: String,
+}
+
+/// Ongoing transfer along with its latest progress.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OngoingTransfer {
+    pub hash: String,
+    /// `None` until the transfer reports progress.
+    pub progress: Option<TransferProgress>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -39,4 +207,7 @@ pub struct ArtifactInfo {
     pub hash: String,
     pub name: String,
     pub path: String,
+    /// Seedbox category, used by the puller to choose the destination.
+    #[serde(default)]
+    pub category: Option<String>,
 }
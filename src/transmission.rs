@@ -0,0 +1,469 @@
+//! Transmission RPC seedbox backend.
+
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use qbit_rs::model::TorrentSource;
+use reqwest::StatusCode;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::{
+    config::TransmissionConfig,
+    seedbox::{Seedbox, matching_tag},
+    task::{TorrentStats, TorrentStatus, TorrentTaskInfo},
+};
+
+const SESSION_ID_HEADER: &str = "X-Transmission-Session-Id";
+
+const TORRENT_FIELDS: &[&str] = &[
+    "hashString",
+    "name",
+    "percentDone",
+    "downloadDir",
+    "addedDate",
+    "labels",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransmissionSeedboxError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Transmission RPC {method} failed: {result}")]
+    Rpc { method: String, result: String },
+    #[error("Transmission did not provide a session id")]
+    MissingSessionId,
+    #[error("Transmission RPC responded with an invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Seedbox backend talking to Transmission through its JSON RPC.
+///
+/// Transmission has no tags, anipler tracks torrents carrying a tracked tag as
+/// label instead.
+pub struct TransmissionSeedbox {
+    client: reqwest::Client,
+    /// Tracked tags in order of precedence.
+    tags: Vec<String>,
+    url: Url,
+    username: Option<String>,
+    password: Option<String>,
+    /// CSRF token handed out by Transmission with `409 Conflict` responses.
+    session_id: Mutex<Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: String,
+    arguments: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct TorrentGetArguments {
+    torrents: Vec<TransmissionTorrent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransmissionTorrent {
+    hash_string: String,
+    name: String,
+    percent_done: f64,
+    download_dir: String,
+    added_date: i64,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TorrentAddArguments {
+    #[serde(rename = "torrent-added")]
+    added: Option<AddedTorrent>,
+    #[serde(rename = "torrent-duplicate")]
+    duplicate: Option<AddedTorrent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddedTorrent {
+    hash_string: String,
+}
+
+impl TransmissionSeedbox {
+    pub fn from_config(config: &TransmissionConfig, tags: Vec<String>) -> Self {
+        tracing::debug!(endpoint = %config.url, "Creating Transmission seedbox client");
+
+        Self {
+            client: reqwest::Client::new(),
+            tags,
+            url: config.url.clone(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            session_id: Mutex::new(None),
+        }
+    }
+
+    /// Call a Transmission RPC method, negotiating the session id if needed.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        arguments: serde_json::Value,
+    ) -> Result<T, TransmissionSeedboxError> {
+        let body = json!({ "method": method, "arguments": arguments });
+
+        // The first attempt might be rejected to hand out a new session id.
+        for _ in 0..2 {
+            let mut request = self.client.post(self.url.clone()).json(&body);
+            if let Some(username) = &self.username {
+                request = request.basic_auth(username, self.password.as_ref());
+            }
+            let session_id = self.session_id.lock().await.clone();
+            if let Some(session_id) = session_id {
+                request = request.header(SESSION_ID_HEADER, session_id);
+            }
+
+            let response = request.send().await?;
+
+            if response.status() == StatusCode::CONFLICT {
+                let session_id = response
+                    .headers()
+                    .get(SESSION_ID_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or(TransmissionSeedboxError::MissingSessionId)?;
+                tracing::trace!("Refreshing Transmission session id");
+                *self.session_id.lock().await = Some(session_id.to_string());
+                continue;
+            }
+
+            let response: RpcResponse<T> = response.error_for_status()?.json().await?;
+            if response.result != "success" {
+                return Err(TransmissionSeedboxError::Rpc {
+                    method: method.to_string(),
+                    result: response.result,
+                });
+            }
+
+            return response.arguments.ok_or_else(|| {
+                TransmissionSeedboxError::InvalidResponse(format!(
+                    "Missing arguments in {method} response"
+                ))
+            });
+        }
+
+        Err(TransmissionSeedboxError::MissingSessionId)
+    }
+
+    async fn get_torrents(
+        &self,
+        hashes: Option<&[String]>,
+    ) -> Result<Vec<TransmissionTorrent>, TransmissionSeedboxError> {
+        let arguments = match hashes {
+            Some(hashes) => json!({ "fields": TORRENT_FIELDS, "ids": hashes }),
+            None => json!({ "fields": TORRENT_FIELDS }),
+        };
+        let response: TorrentGetArguments = self.call("torrent-get", arguments).await?;
+
+        Ok(response.torrents)
+    }
+
+    /// Add a single torrent and make sure it carries the given label.
+    async fn add_torrent(
+        &self,
+        mut arguments: serde_json::Value,
+        tag: &str,
+    ) -> Result<TorrentTaskInfo, TransmissionSeedboxError> {
+        arguments["labels"] = json!([tag]);
+        let response: TorrentAddArguments = self.call("torrent-add", arguments).await?;
+        let hash = response
+            .added
+            .or(response.duplicate)
+            .ok_or_else(|| {
+                TransmissionSeedboxError::InvalidResponse(
+                    "Missing torrent in torrent-add response".to_string(),
+                )
+            })?
+            .hash_string;
+
+        let torrent = self
+            .get_torrents(Some(std::slice::from_ref(&hash)))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                TransmissionSeedboxError::InvalidResponse(format!("Added torrent {hash} not found"))
+            })?;
+
+        // Labels are ignored when the torrent already exists.
+        if !torrent.labels.iter().any(|label| label == tag) {
+            let mut labels = torrent.labels.clone();
+            labels.push(tag.to_string());
+            let _: serde_json::Value = self
+                .call("torrent-set", json!({ "ids": [hash], "labels": labels }))
+                .await?;
+        }
+
+        Ok(torrent.into_task_info(tag.to_string()))
+    }
+}
+
+impl TransmissionTorrent {
+    fn tracked_tag(&self, tracked: &[String]) -> Option<String> {
+        let labels = self.labels.iter().map(String::as_str).collect::<Vec<_>>();
+        matching_tag(tracked, &labels)
+    }
+
+    fn into_task_info(self, tag: String) -> TorrentTaskInfo {
+        let status = if self.percent_done < 1.0 {
+            TorrentStatus::Downloading
+        } else {
+            TorrentStatus::Seeding
+        };
+        let content_path = format!("{}/{}", self.download_dir.trim_end_matches('/'), self.name);
+
+        TorrentTaskInfo {
+            hash: self.hash_string,
+            status,
+            content_path,
+            name: self.name,
+            tag,
+            // Transmission has no categories, labels are used for tags.
+            category: None,
+            save_path: Some(self.download_dir),
+            stats: TorrentStats {
+                progress: self.percent_done,
+                ..TorrentStats::default()
+            },
+            seedbox: String::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Seedbox for TransmissionSeedbox {
+    async fn upload_torrent(
+        &self,
+        source: &TorrentSource,
+        tag: &str,
+    ) -> anyhow::Result<TorrentTaskInfo> {
+        let requests = match source {
+            // `Sep` renders as newline separated list.
+            TorrentSource::Urls { urls } => urls
+                .to_string()
+                .lines()
+                .map(|url| json!({ "filename": url }))
+                .collect::<Vec<_>>(),
+            TorrentSource::TorrentFiles { torrents } => torrents
+                .iter()
+                .map(|t| json!({ "metainfo": BASE64_STANDARD.encode(&t.data) }))
+                .collect(),
+        };
+
+        let mut uploaded = None;
+        for arguments in requests {
+            let info = self.add_torrent(arguments, tag).await?;
+            tracing::info!(torrent = %info.name, hash = %info.hash, "Uploaded torrent");
+            uploaded.get_or_insert(info);
+        }
+
+        uploaded.ok_or_else(|| anyhow::anyhow!("No torrent given for upload"))
+    }
+
+    async fn query_torrents(
+        &self,
+        earliest_import_date: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<TorrentTaskInfo>> {
+        tracing::debug!("Querying Transmission RPC for torrents");
+
+        let mut ignored_count = 0;
+        let mut tracked_count = 0;
+
+        let torrents = self
+            .get_torrents(None)
+            .await?
+            .into_iter()
+            .filter_map(|t| t.tracked_tag(&self.tags).map(|tag| (t, tag)))
+            .filter_map(|(t, tag)| {
+                if t.added_date < earliest_import_date.timestamp() {
+                    ignored_count += 1;
+                    tracing::trace!(torrent = %t.name, hash = %t.hash_string, "Ignoring torrent");
+                    return None;
+                }
+
+                tracked_count += 1;
+                tracing::trace!(torrent = %t.name, hash = %t.hash_string, "Tracking torrent");
+
+                Some(t.into_task_info(tag))
+            })
+            .collect::<Vec<_>>();
+
+        tracing::debug!(
+            tracked = tracked_count,
+            ignored = ignored_count,
+            "Queried torrents from RPC"
+        );
+
+        Ok(torrents)
+    }
+
+    async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>> {
+        let torrents = self
+            .get_torrents(None)
+            .await?
+            .into_iter()
+            .filter_map(|t| t.tracked_tag(&self.tags).map(|tag| (t, tag)))
+            .map(|(t, tag)| {
+                let added = DateTime::from_timestamp(t.added_date, 0).unwrap_or_default();
+                (t.into_task_info(tag), added)
+            })
+            .collect();
+
+        Ok(torrents)
+    }
+
+    async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct TorrentHash {
+            hash_string: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Arguments {
+            torrents: Vec<TorrentHash>,
+        }
+
+        let response: Arguments = self
+            .call("torrent-get", json!({ "fields": ["hashString"] }))
+            .await?;
+
+        Ok(response
+            .torrents
+            .into_iter()
+            .map(|t| t.hash_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, http::HeaderMap, response::IntoResponse, routing::post};
+
+    use super::*;
+
+    const MOCK_SESSION_ID: &str = "mock-session";
+
+    async fn mock_rpc(
+        headers: HeaderMap,
+        Json(body): Json<serde_json::Value>,
+    ) -> impl IntoResponse {
+        if headers
+            .get(SESSION_ID_HEADER)
+            .is_none_or(|v| v.as_bytes() != MOCK_SESSION_ID.as_bytes())
+        {
+            let mut headers = HeaderMap::new();
+            headers.insert(SESSION_ID_HEADER, MOCK_SESSION_ID.parse().unwrap());
+            return (StatusCode::CONFLICT, headers, Json(json!({}))).into_response();
+        }
+
+        let response = match body["method"].as_str() {
+            Some("torrent-get") => json!({
+                "result": "success",
+                "arguments": {
+                    "torrents": [
+                        {
+                            "hashString": "aaaa",
+                            "name": "Show - 01",
+                            "percentDone": 0.5,
+                            "downloadDir": "/downloads/",
+                            "addedDate": 2_000_000_000,
+                            "labels": ["anipler"]
+                        },
+                        {
+                            "hashString": "bbbb",
+                            "name": "Show - 02",
+                            "percentDone": 1.0,
+                            "downloadDir": "/downloads",
+                            "addedDate": 2_000_000_000,
+                            "labels": ["anime", "anipler"]
+                        },
+                        {
+                            "hashString": "cccc",
+                            "name": "Unrelated",
+                            "percentDone": 1.0,
+                            "downloadDir": "/downloads",
+                            "addedDate": 2_000_000_000,
+                            "labels": []
+                        },
+                        {
+                            "hashString": "dddd",
+                            "name": "Old",
+                            "percentDone": 1.0,
+                            "downloadDir": "/downloads",
+                            "addedDate": 0,
+                            "labels": ["anipler"]
+                        }
+                    ]
+                }
+            }),
+            _ => json!({ "result": "method name not recognized" }),
+        };
+
+        Json(response).into_response()
+    }
+
+    async fn spawn_mock_server() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/transmission/rpc", post(mock_rpc));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Url::parse(&format!("http://{addr}/transmission/rpc")).unwrap()
+    }
+
+    fn seedbox(url: Url) -> TransmissionSeedbox {
+        TransmissionSeedbox::from_config(
+            &TransmissionConfig {
+                url,
+                username: None,
+                password: None,
+            },
+            vec!["anipler".to_string(), "anime".to_string()],
+        )
+    }
+
+    #[tokio::test]
+    async fn query_torrents_filters_by_label_and_maps_fields() {
+        let seedbox = seedbox(spawn_mock_server().await);
+
+        let torrents = seedbox
+            .query_torrents(DateTime::from_timestamp(1_000_000_000, 0).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(torrents.len(), 2);
+        assert_eq!(torrents[0].hash, "aaaa");
+        assert!(matches!(torrents[0].status, TorrentStatus::Downloading));
+        assert_eq!(torrents[0].content_path, "/downloads/Show - 01");
+        assert_eq!(torrents[0].tag, "anipler");
+        assert_eq!(torrents[1].hash, "bbbb");
+        assert!(matches!(torrents[1].status, TorrentStatus::Seeding));
+        assert_eq!(torrents[1].content_path, "/downloads/Show - 02");
+        assert_eq!(torrents[1].tag, "anipler");
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_are_reported() {
+        let seedbox = seedbox(spawn_mock_server().await);
+
+        let err = seedbox
+            .call::<serde_json::Value>("session-get", json!({}))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TransmissionSeedboxError::Rpc { .. }));
+    }
+}
@@ -0,0 +1,184 @@
+//! Submission of new torrents to a seedbox.
+
+use qbit_rs::model::TorrentSource;
+
+use crate::{
+    seedbox::SeedboxSet,
+    storage::{StorageManager, StorageManagerError},
+    task::TorrentTaskInfo,
+};
+
+/// Add a torrent to a seedbox and start tracking it immediately.
+///
+/// The torrent is tagged with `tag`, or the first of the tracked `tags` if not
+/// given, and added to the named seedbox, or the primary one if not given.
+///
+/// # Errors
+///
+/// Returns an error if the tag is not tracked, the seedbox is unknown, the
+/// seedbox rejects the torrent or updating the storage fails.
+pub async fn upload_torrent(
+    store: &StorageManager,
+    seedboxes: &SeedboxSet,
+    tags: &[String],
+    source: &TorrentSource,
+    tag: Option<&str>,
+    seedbox: Option<&str>,
+) -> Result<TorrentTaskInfo, UploadError> {
+    let tag = match tag {
+        Some(tag) if !tags.iter().any(|tracked| tracked == tag) => {
+            return Err(UploadError::UntrackedTag(tag.to_string()));
+        }
+        Some(tag) => tag,
+        None => tags
+            .first()
+            .ok_or_else(|| UploadError::UntrackedTag(String::new()))?,
+    };
+    let (name, client) = match seedbox {
+        Some(name) => (
+            name,
+            seedboxes
+                .get(name)
+                .ok_or_else(|| UploadError::UnknownSeedbox(name.to_string()))?,
+        ),
+        None => seedboxes.primary(),
+    };
+
+    tracing::info!(tag = %tag, seedbox = %name, "Uploading torrent to seedbox");
+
+    let mut torrent = client
+        .upload_torrent(source, tag)
+        .await
+        .map_err(UploadError::Seedbox)?;
+    torrent.seedbox = name.to_string();
+    store
+        .update_torrent_info(std::slice::from_ref(&torrent))
+        .await?;
+    tracing::info!(torrent = %torrent.name, hash = %torrent.hash, "Tracking uploaded torrent");
+
+    Ok(torrent)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Tag {0} is not tracked")]
+    UntrackedTag(String),
+    #[error("Unknown seedbox {0}")]
+    UnknownSeedbox(String),
+    #[error("Failed to upload torrent: {0}")]
+    Seedbox(anyhow::Error),
+    #[error("Failed to track uploaded torrent: {0}")]
+    Storage(#[from] StorageManagerError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc};
+
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::{
+        seedbox::Seedbox,
+        task::{TorrentStats, TorrentStatus},
+    };
+
+    /// Seedbox accepting every torrent as a new download.
+    struct MockSeedbox;
+
+    #[async_trait]
+    impl Seedbox for MockSeedbox {
+        async fn upload_torrent(
+            &self,
+            _source: &TorrentSource,
+            tag: &str,
+        ) -> anyhow::Result<TorrentTaskInfo> {
+            Ok(TorrentTaskInfo {
+                hash: "0123abcd".to_string(),
+                status: TorrentStatus::Downloading,
+                content_path: "/downloads/Show".to_string(),
+                name: "Show".to_string(),
+                tag: tag.to_string(),
+                category: None,
+                save_path: Some("/downloads".to_string()),
+                stats: TorrentStats::default(),
+                seedbox: String::new(),
+            })
+        }
+
+        async fn query_torrents(
+            &self,
+            _earliest_import_date: DateTime<Utc>,
+        ) -> anyhow::Result<Vec<TorrentTaskInfo>> {
+            Ok(Vec::new())
+        }
+
+        async fn tracked_torrents(&self) -> anyhow::Result<Vec<(TorrentTaskInfo, DateTime<Utc>)>> {
+            Ok(Vec::new())
+        }
+
+        async fn torrent_hashes(&self) -> anyhow::Result<HashSet<String>> {
+            Ok(HashSet::new())
+        }
+    }
+
+    fn seedboxes() -> SeedboxSet {
+        SeedboxSet::from_clients(vec![
+            ("primary".to_string(), Arc::new(MockSeedbox)),
+            ("backup".to_string(), Arc::new(MockSeedbox)),
+        ])
+    }
+
+    fn source() -> TorrentSource {
+        TorrentSource::Urls {
+            urls: vec!["magnet:?xt=urn:btih:0123abcd".parse().unwrap()].into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn uploaded_torrents_are_tracked_with_tag_and_seedbox() {
+        let store = StorageManager::in_memory().await;
+        let tags = ["anipler".to_string(), "movies".to_string()];
+
+        let torrent = upload_torrent(
+            &store,
+            &seedboxes(),
+            &tags,
+            &source(),
+            Some("movies"),
+            Some("backup"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(torrent.seedbox, "backup");
+
+        let tracked = store.list_tracked_torrents().await.unwrap();
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].hash, "0123abcd");
+        assert_eq!(tracked[0].tag, "movies");
+        assert_eq!(tracked[0].seedbox, "backup");
+    }
+
+    #[tokio::test]
+    async fn uploads_default_to_first_tag_and_primary_seedbox() {
+        let store = StorageManager::in_memory().await;
+        let tags = ["anipler".to_string(), "movies".to_string()];
+
+        upload_torrent(&store, &seedboxes(), &tags, &source(), None, None)
+            .await
+            .unwrap();
+        let tracked = store.list_tracked_torrents().await.unwrap();
+        assert_eq!(tracked[0].tag, "anipler");
+        assert_eq!(tracked[0].seedbox, "primary");
+
+        assert!(matches!(
+            upload_torrent(&store, &seedboxes(), &tags, &source(), Some("other"), None).await,
+            Err(UploadError::UntrackedTag(tag)) if tag == "other"
+        ));
+        assert!(matches!(
+            upload_torrent(&store, &seedboxes(), &tags, &source(), None, Some("other")).await,
+            Err(UploadError::UnknownSeedbox(name)) if name == "other"
+        ));
+    }
+}
@@ -0,0 +1,445 @@
+//! Minimal XML-RPC encoding and decoding, as spoken by rTorrent.
+
+use std::fmt::Write;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+
+#[derive(Debug, thiserror::Error)]
+pub enum XmlRpcError {
+    #[error("malformed XML-RPC document: {0}")]
+    Malformed(String),
+    #[error("XML-RPC fault {code}: {message}")]
+    Fault { code: i64, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    Double(f64),
+    String(String),
+    Base64(Vec<u8>),
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+    Nil,
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            Self::Bool(b) => Some(i64::from(*b)),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn write_xml(&self, out: &mut String) {
+        out.push_str("<value>");
+        match self {
+            Self::Int(i) => {
+                let _ = write!(out, "<i8>{i}</i8>");
+            }
+            Self::Bool(b) => {
+                let _ = write!(out, "<boolean>{}</boolean>", u8::from(*b));
+            }
+            Self::Double(d) => {
+                let _ = write!(out, "<double>{d}</double>");
+            }
+            Self::String(s) => {
+                out.push_str("<string>");
+                escape_into(s, out);
+                out.push_str("</string>");
+            }
+            Self::Base64(data) => {
+                out.push_str("<base64>");
+                out.push_str(&BASE64_STANDARD.encode(data));
+                out.push_str("</base64>");
+            }
+            Self::Array(values) => {
+                out.push_str("<array><data>");
+                for value in values {
+                    value.write_xml(out);
+                }
+                out.push_str("</data></array>");
+            }
+            Self::Struct(members) => {
+                out.push_str("<struct>");
+                for (name, value) in members {
+                    out.push_str("<member><name>");
+                    escape_into(name, out);
+                    out.push_str("</name>");
+                    value.write_xml(out);
+                    out.push_str("</member>");
+                }
+                out.push_str("</struct>");
+            }
+            Self::Nil => out.push_str("<nil/>"),
+        }
+        out.push_str("</value>");
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+/// Encode a `methodCall` document.
+pub fn encode_call(method: &str, params: &[Value]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\"?><methodCall><methodName>");
+    escape_into(method, &mut out);
+    out.push_str("</methodName><params>");
+    for param in params {
+        out.push_str("<param>");
+        param.write_xml(&mut out);
+        out.push_str("</param>");
+    }
+    out.push_str("</params></methodCall>");
+    out
+}
+
+/// Decode a `methodResponse` document into its single return value.
+///
+/// # Errors
+///
+/// Returns [`XmlRpcError::Fault`] if the response is a fault, or
+/// [`XmlRpcError::Malformed`] if the document can not be parsed.
+pub fn decode_response(xml: &str) -> Result<Value, XmlRpcError> {
+    let mut parser = Parser { input: xml, pos: 0 };
+
+    parser.skip_prolog();
+    parser.expect_open("methodResponse")?;
+
+    let response = match parser.open_tag()? {
+        Tag::Open("params") => {
+            parser.expect_open("param")?;
+            let value = parser.value()?;
+            parser.expect_close("param")?;
+            parser.expect_close("params")?;
+            Ok(value)
+        }
+        Tag::Open("fault") => {
+            let fault = parser.value()?;
+            parser.expect_close("fault")?;
+            Err(fault_error(&fault))
+        }
+        tag => Err(XmlRpcError::Malformed(format!(
+            "unexpected {tag:?} in methodResponse"
+        ))),
+    };
+
+    parser.expect_close("methodResponse")?;
+    response
+}
+
+fn fault_error(fault: &Value) -> XmlRpcError {
+    let Value::Struct(members) = fault else {
+        return XmlRpcError::Malformed("fault is not a struct".to_string());
+    };
+    let member = |name: &str| {
+        members
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    };
+
+    XmlRpcError::Fault {
+        code: member("faultCode").and_then(Value::as_i64).unwrap_or(0),
+        message: member("faultString")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String, XmlRpcError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| XmlRpcError::Malformed("unterminated entity".to_string()))?;
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32)
+                .ok_or_else(|| XmlRpcError::Malformed(format!("unknown entity &{entity};")))?,
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Tag<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    Empty(&'a str),
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_prolog(&mut self) {
+        self.skip_whitespace();
+        if self.rest().starts_with("<?")
+            && let Some(end) = self.rest().find("?>")
+        {
+            self.pos += end + 2;
+        }
+    }
+
+    /// Consume character data up to the next tag.
+    fn text(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find('<').unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn peek_tag(&self) -> Result<Tag<'a>, XmlRpcError> {
+        let rest = self.rest().trim_start();
+        let body = rest
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+            .map(|(body, _)| body)
+            .ok_or_else(|| XmlRpcError::Malformed("expected a tag".to_string()))?;
+
+        if let Some(name) = body.strip_prefix('/') {
+            Ok(Tag::Close(name.trim()))
+        } else if let Some(name) = body.strip_suffix('/') {
+            Ok(Tag::Empty(name.trim()))
+        } else {
+            Ok(Tag::Open(
+                body.split_whitespace().next().unwrap_or_default(),
+            ))
+        }
+    }
+
+    fn open_tag(&mut self) -> Result<Tag<'a>, XmlRpcError> {
+        let tag = self.peek_tag()?;
+        self.skip_whitespace();
+        // `peek_tag` guarantees the closing bracket exists.
+        self.pos += self.rest().find('>').unwrap_or_default() + 1;
+        Ok(tag)
+    }
+
+    fn expect_open(&mut self, name: &str) -> Result<(), XmlRpcError> {
+        match self.open_tag()? {
+            Tag::Open(n) if n == name => Ok(()),
+            tag => Err(XmlRpcError::Malformed(format!(
+                "expected <{name}>, found {tag:?}"
+            ))),
+        }
+    }
+
+    fn expect_close(&mut self, name: &str) -> Result<(), XmlRpcError> {
+        match self.open_tag()? {
+            Tag::Close(n) if n == name => Ok(()),
+            tag => Err(XmlRpcError::Malformed(format!(
+                "expected </{name}>, found {tag:?}"
+            ))),
+        }
+    }
+
+    /// Parse a `<value>` element.
+    fn value(&mut self) -> Result<Value, XmlRpcError> {
+        match self.open_tag()? {
+            Tag::Empty("value") => return Ok(Value::String(String::new())),
+            Tag::Open("value") => {}
+            tag => {
+                return Err(XmlRpcError::Malformed(format!(
+                    "expected <value>, found {tag:?}"
+                )));
+            }
+        }
+
+        // Untyped values are strings.
+        let text = self.text();
+        if let Tag::Close("value") = self.peek_tag()? {
+            self.open_tag()?;
+            return Ok(Value::String(unescape(text)?));
+        }
+
+        let value = match self.open_tag()? {
+            Tag::Empty("nil") => Value::Nil,
+            Tag::Empty("string") => Value::String(String::new()),
+            Tag::Empty("array") => Value::Array(Vec::new()),
+            Tag::Empty("struct") => Value::Struct(Vec::new()),
+            Tag::Open(ty @ ("i4" | "i8" | "int" | "boolean" | "double" | "string" | "base64")) => {
+                let text = unescape(self.text())?;
+                self.expect_close(ty)?;
+                scalar(ty, text)?
+            }
+            Tag::Open("array") => {
+                let mut values = Vec::new();
+                match self.open_tag()? {
+                    Tag::Empty("data") => {}
+                    Tag::Open("data") => {
+                        while !matches!(self.peek_tag()?, Tag::Close(_)) {
+                            values.push(self.value()?);
+                        }
+                        self.expect_close("data")?;
+                    }
+                    tag => {
+                        return Err(XmlRpcError::Malformed(format!(
+                            "expected <data>, found {tag:?}"
+                        )));
+                    }
+                }
+                self.expect_close("array")?;
+                Value::Array(values)
+            }
+            Tag::Open("struct") => {
+                let mut members = Vec::new();
+                while !matches!(self.peek_tag()?, Tag::Close(_)) {
+                    self.expect_open("member")?;
+                    self.expect_open("name")?;
+                    let name = unescape(self.text())?;
+                    self.expect_close("name")?;
+                    members.push((name, self.value()?));
+                    self.expect_close("member")?;
+                }
+                self.expect_close("struct")?;
+                Value::Struct(members)
+            }
+            tag => {
+                return Err(XmlRpcError::Malformed(format!(
+                    "unexpected {tag:?} in value"
+                )));
+            }
+        };
+
+        self.expect_close("value")?;
+        Ok(value)
+    }
+}
+
+fn scalar(ty: &str, text: String) -> Result<Value, XmlRpcError> {
+    let invalid = |e: &dyn std::fmt::Display| XmlRpcError::Malformed(format!("invalid {ty}: {e}"));
+
+    Ok(match ty {
+        "boolean" => Value::Bool(text.trim() == "1"),
+        "double" => Value::Double(text.trim().parse().map_err(|e| invalid(&e))?),
+        "string" => Value::String(text),
+        "base64" => Value::Base64(
+            BASE64_STANDARD
+                .decode(text.trim())
+                .map_err(|e| invalid(&e))?,
+        ),
+        _ => Value::Int(text.trim().parse().map_err(|e| invalid(&e))?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_nested_arrays() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<methodResponse>
+<params>
+<param><value><array><data>
+<value><array><data>
+<value><string>ABCD</string></value>
+<value>Show &amp; Tell</value>
+<value><i8>1</i8></value>
+<value><string/></value>
+</data></array></value>
+</data></array></value></param>
+</params>
+</methodResponse>"#;
+
+        let value = decode_response(xml).unwrap();
+
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::Array(vec![
+                Value::String("ABCD".to_string()),
+                Value::String("Show & Tell".to_string()),
+                Value::Int(1),
+                Value::String(String::new()),
+            ])])
+        );
+    }
+
+    #[test]
+    fn decodes_faults() {
+        let xml = "<?xml version=\"1.0\"?><methodResponse><fault><value><struct>\
+<member><name>faultCode</name><value><i4>-506</i4></value></member>\
+<member><name>faultString</name><value><string>Method not defined</string></value></member>\
+</struct></value></fault></methodResponse>";
+
+        let err = decode_response(xml).unwrap_err();
+
+        assert!(matches!(err, XmlRpcError::Fault { code: -506, .. }));
+    }
+
+    #[test]
+    fn encodes_calls() {
+        let xml = encode_call("d.multicall2", &[Value::from(""), Value::from("a<b")]);
+
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\"?><methodCall><methodName>d.multicall2</methodName><params>\
+<param><value><string></string></value></param>\
+<param><value><string>a&lt;b</string></value></param>\
+</params></methodCall>"
+        );
+    }
+}